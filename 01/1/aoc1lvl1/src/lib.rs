@@ -1,66 +1,369 @@
-use std::fs;
-use std::io;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Stdout};
 use std::path::PathBuf;
+use std::time::Duration;
 
 extern crate clap;
 use clap::Parser;
 
 extern crate anyhow;
 
+extern crate aoccommon;
+use aoccommon::alloc;
+use aoccommon::batch;
+use aoccommon::config::{AocConfig, OutputFormat};
+use aoccommon::report::{Field, ReportWriter};
+
 #[derive(Parser, Debug)]
 pub struct Args {
     /// path to the input file
     #[arg(short, long)]
     input: Option<PathBuf>,
+    /// path to a directory of input files to solve in parallel
+    #[arg(long, conflicts_with = "input")]
+    inputs: Option<PathBuf>,
+    /// skip lines without any digits instead of failing on them
+    #[arg(long)]
+    skip_invalid: bool,
+    /// read the input a line at a time instead of loading it all into memory
+    #[arg(long, conflicts_with = "inputs")]
+    stream: bool,
+    /// print each line's first and last digit and its value instead of just the sum,
+    /// as text, json or csv (defaults to output_format from aoc.toml)
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1, conflicts_with = "inputs")]
+    report: Option<Option<OutputFormat>>,
 }
 
 enum InputConfig {
     File(PathBuf),
     Stdin,
+    Batch(PathBuf, Option<Duration>),
 }
 pub struct Config {
     input: InputConfig,
+    skip_invalid: bool,
+    stream: bool,
+    report: Option<OutputFormat>,
 }
 
 impl Config {
-    pub fn make() -> Self {
+    pub fn make() -> anyhow::Result<Self> {
         let args = Args::parse();
-        let input = if let Some(path) = args.input {
+        let file_cfg = AocConfig::load()?;
+        // command line input wins over the config file
+        let input = if let Some(dir) = args.inputs {
+            InputConfig::Batch(dir, file_cfg.timeout())
+        } else if let Some(path) = args
+            .input
+            .map(Ok)
+            .or_else(|| file_cfg.input_path(1).transpose())
+            .transpose()?
+        {
             InputConfig::File(path)
         } else {
             InputConfig::Stdin
         };
 
-        Config { input }
+        Ok(Config {
+            input,
+            skip_invalid: args.skip_invalid,
+            stream: args.stream,
+            report: args
+                .report
+                .map(|format| format.unwrap_or(file_cfg.output_format)),
+        })
     }
 }
 
 pub fn run(cfg: Config) -> anyhow::Result<()> {
-    // figure out where to get our input from and read it into a string
-    let input_string = match cfg.input {
-        InputConfig::File(path) => fs::read_to_string(path)?,
-        InputConfig::Stdin => {
-            let mut buf = String::new();
-            io::stdin().read_line(&mut buf)?;
-            buf
+    let result = if cfg.stream {
+        match cfg.input {
+            InputConfig::File(path) => stream(
+                BufReader::new(File::open(path)?),
+                cfg.skip_invalid,
+                cfg.report,
+            )?,
+            InputConfig::Stdin => stream(io::stdin().lock(), cfg.skip_invalid, cfg.report)?,
+            InputConfig::Batch(..) => unreachable!("clap rejects --stream with --inputs"),
         }
-    };
+    } else {
+        // figure out where to get our input from and read it into a string
+        let input_string = match cfg.input {
+            InputConfig::File(path) => fs::read_to_string(path)?,
+            InputConfig::Stdin => {
+                let mut buf = String::new();
+                io::stdin().read_line(&mut buf)?;
+                buf
+            }
+            InputConfig::Batch(dir, timeout) => {
+                let report = batch::run_batch(&dir, timeout, move |input| {
+                    solve(input, cfg.skip_invalid, None)
+                })?;
+                println!("{}", report);
+                if report.failures() > 0 {
+                    anyhow::bail!(
+                        "{} of {} inputs failed",
+                        report.failures(),
+                        report.results.len()
+                    );
+                }
+                return Ok(());
+            }
+        };
 
-    let data = parse(input_string)?;
-    let result = process(data);
+        solve(input_string, cfg.skip_invalid, cfg.report)?
+    };
 
-    println!("{}", result);
+    if result.skipped > 0 {
+        eprintln!("skipped {} lines without a digit", result.skipped);
+    }
+    // the report has stdout to itself so it can be piped straight into other tools
+    if cfg.report.is_some() {
+        eprintln!("total: {}", result.sum);
+    } else {
+        println!("{}", result.sum);
+    }
 
     Ok(())
 }
 
+/// parse and process a single input
+fn solve(input: String, skip_invalid: bool, report: Option<OutputFormat>) -> anyhow::Result<Total> {
+    let data = alloc::measure("parse", || parse(input))?;
+    let tally = alloc::measure("solve", || process(data, skip_invalid, report))?;
+    tally.finish()
+}
+
+/// sum the calibration values of a reader without holding more than one line in memory
+fn stream(
+    mut reader: impl BufRead,
+    skip_invalid: bool,
+    report: Option<OutputFormat>,
+) -> anyhow::Result<Total> {
+    let mut tally = Tally::new(report);
+    // one buffer reused for every line so memory stays flat however long the input is
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        tally.add(line.trim_end_matches(['\n', '\r']), skip_invalid)?;
+        line.clear();
+    }
+    tally.finish()
+}
+
 fn parse(input: String) -> anyhow::Result<Vec<String>> {
     Ok(input.lines().map(|s| s.to_string()).collect())
 }
 
-fn process(data: Vec<String>) -> u32 {
-    data.into_iter()
-        .map(|line| line.chars().filter(|c| c.is_numeric()).collect::<Vec<_>>())
-        .map(|v| v[0].to_digit(10).unwrap() * 10 + v[v.len() - 1].to_digit(10).unwrap())
-        .sum()
+/// add up every line's calibration value
+fn process(
+    data: Vec<String>,
+    skip_invalid: bool,
+    report: Option<OutputFormat>,
+) -> anyhow::Result<Tally> {
+    let mut tally = Tally::new(report);
+    for line in data.iter() {
+        tally.add(line, skip_invalid)?;
+    }
+    Ok(tally)
+}
+
+const REPORT_COLUMNS: &[&str] = &[
+    "line",
+    "first",
+    "first_start",
+    "first_end",
+    "last",
+    "last_start",
+    "last_end",
+    "value",
+];
+
+/// a digit on a line and the byte offset it's at
+type Digit = (usize, u32);
+
+/// running total of calibration values, fed one line at a time
+struct Tally {
+    // u64 so even billions of lines of 99s can't overflow
+    sum: u64,
+    lines: usize,
+    skipped: usize,
+    report: Option<ReportWriter<BufWriter<Stdout>>>,
+}
+
+impl Tally {
+    fn new(report: Option<OutputFormat>) -> Self {
+        Tally {
+            sum: 0,
+            lines: 0,
+            skipped: 0,
+            report: report.map(|format| {
+                ReportWriter::new(BufWriter::new(io::stdout()), format, REPORT_COLUMNS)
+            }),
+        }
+    }
+
+    fn add(&mut self, line: &str, skip_invalid: bool) -> anyhow::Result<()> {
+        self.lines += 1;
+        let found = first_and_last(line);
+        match found {
+            Some((first, last)) => self.sum += u64::from(calibration_value(first, last)),
+            None if skip_invalid => self.skipped += 1,
+            None => anyhow::bail!("line {}: no digit found in {:?}", self.lines, line),
+        }
+
+        if let Some(report) = &mut self.report {
+            report.row(report_row(self.lines, found))?;
+        }
+        Ok(())
+    }
+
+    /// close off the report and hand back the sum
+    fn finish(self) -> anyhow::Result<Total> {
+        if let Some(report) = self.report {
+            report.finish()?;
+        }
+        Ok(Total {
+            sum: self.sum,
+            skipped: self.skipped,
+        })
+    }
+}
+
+/// a line's report row, in the order of REPORT_COLUMNS
+fn report_row(number: usize, found: Option<(Digit, Digit)>) -> Vec<Field> {
+    let (first, last) = found.unzip();
+    vec![
+        number.into(),
+        first.map(|(_, d)| d.to_string()).as_deref().into(),
+        first.map(|(i, _)| i).into(),
+        first.map(|(i, _)| i + 1).into(),
+        last.map(|(_, d)| d.to_string()).as_deref().into(),
+        last.map(|(i, _)| i).into(),
+        last.map(|(i, _)| i + 1).into(),
+        found
+            .map(|(first, last)| calibration_value(first, last))
+            .into(),
+    ]
+}
+
+/// the sum of an input and how many lines it had to skip to get there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Total {
+    sum: u64,
+    skipped: usize,
+}
+
+/// the sum, and the skipped lines if there were any, so each file of a batch says how many
+/// of its own lines it skipped
+impl fmt::Display for Total {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.sum)?;
+        if self.skipped > 0 {
+            write!(f, ", skipped {} lines without a digit", self.skipped)?;
+        }
+        Ok(())
+    }
+}
+
+/// the first and last digit on a line, if there are any digits
+fn first_and_last(line: &str) -> Option<(Digit, Digit)> {
+    let digit = |(i, c): (usize, char)| c.to_digit(10).map(|d| (i, d));
+    let first = line.char_indices().find_map(digit)?;
+    let last = line.char_indices().rev().find_map(digit)?;
+    Some((first, last))
+}
+
+/// the first and last digit as a two digit number
+fn calibration_value((_, first): Digit, (_, last): Digit) -> u32 {
+    first * 10 + last
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total(input: &str, skip_invalid: bool) -> anyhow::Result<Total> {
+        solve(input.to_string(), skip_invalid, None)
+    }
+
+    #[test]
+    fn example() {
+        let input = "1abc2\npqr3stu8vwx\na1b2c3d4e5f\ntreb7uchet\n";
+        assert_eq!(total(input, false).unwrap().sum, 142);
+    }
+
+    #[test]
+    fn lines_without_a_digit_say_which_line() {
+        let err = total("1abc2\nxyz\n3\n", false).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: no digit found in \"xyz\"");
+    }
+
+    #[test]
+    fn skipped_lines_are_counted() {
+        let result = total("1abc2\nxyz\n\n3\nnope", true).unwrap();
+        assert_eq!(
+            result,
+            Total {
+                sum: 45,
+                skipped: 3
+            }
+        );
+        assert_eq!(result.to_string(), "45, skipped 3 lines without a digit");
+        assert_eq!(total("1abc2", true).unwrap().to_string(), "12");
+    }
+
+    #[test]
+    fn a_final_newline_is_not_a_line() {
+        assert_eq!(total("1abc2\n", false).unwrap().sum, 12);
+        // but a blank line after it is, and it has no digit
+        let err = total("1abc2\n\n", false).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: no digit found in \"\"");
+        assert_eq!(total("1abc2\n\n", true).unwrap().skipped, 1);
+    }
+
+    #[test]
+    fn streaming_matches_reading_it_all() {
+        for input in [
+            "1abc2\npqr3stu8vwx\na1b2c3d4e5f\ntreb7uchet\n",
+            "1abc2\r\npqr3stu8vwx\r\nxyz\r\ntreb7uchet\r\n",
+            "1abc2\npqr3stu8vwx\ntreb7uchet",
+            "1abc2\r\n\r\ntreb7uchet",
+            "",
+        ] {
+            let streamed = stream(input.as_bytes(), true, None).unwrap();
+            assert_eq!(streamed, total(input, true).unwrap(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn streaming_errors_match_too() {
+        let input = "1\r\nxyz\r\n";
+        let streamed = stream(input.as_bytes(), false, None).unwrap_err();
+        assert_eq!(
+            streamed.to_string(),
+            total(input, false).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn report_rows_give_byte_offsets() {
+        let row = report_row(3, first_and_last("ü1x2"));
+        assert_eq!(
+            row,
+            vec![
+                Field::Number(3),
+                Field::Text("1".to_string()),
+                Field::Number(2),
+                Field::Number(3),
+                Field::Text("2".to_string()),
+                Field::Number(4),
+                Field::Number(5),
+                Field::Number(12),
+            ]
+        );
+        assert!(report_row(4, None)[1..]
+            .iter()
+            .all(|field| *field == Field::Empty));
+    }
 }
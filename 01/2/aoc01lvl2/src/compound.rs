@@ -0,0 +1,211 @@
+//! english number phrases written without spaces, like "twentythree" or "onehundredone"
+//!
+//! a phrase is anything from one up to nine hundred ninety nine thousand nine hundred ninety
+//! nine, built from the usual pieces: units, teens, tens optionally followed by a unit, then
+//! "hundred" and "thousand" groups. digits still count, one at a time.
+//!
+//! phrases can overlap and nest ("eighteen" contains "eight", "twentyone" contains "one"), so
+//! we settle it like this:
+//! - the first number is the longest phrase starting furthest left, its leading digit counts
+//! - the last number is the longest phrase ending furthest right, its final digit counts
+//!
+//! so "eighteen" is 18 rather than 8, "onehundred" gives 1 and 0, and "eightwo" is still 8 then 2
+
+use crate::matcher::{Find, Match};
+
+const UNITS: [(&str, u32); 9] = [
+    ("one", 1),
+    ("two", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("seven", 7),
+    ("eight", 8),
+    ("nine", 9),
+];
+
+const TEENS: [(&str, u32); 10] = [
+    ("ten", 10),
+    ("eleven", 11),
+    ("twelve", 12),
+    ("thirteen", 13),
+    ("fourteen", 14),
+    ("fifteen", 15),
+    ("sixteen", 16),
+    ("seventeen", 17),
+    ("eighteen", 18),
+    ("nineteen", 19),
+];
+
+const TENS: [(&str, u32); 8] = [
+    ("twenty", 20),
+    ("thirty", 30),
+    ("forty", 40),
+    ("fifty", 50),
+    ("sixty", 60),
+    ("seventy", 70),
+    ("eighty", 80),
+    ("ninety", 90),
+];
+
+/// finds digits and english number phrases
+pub struct CompoundMatcher;
+
+impl Find for CompoundMatcher {
+    fn first(&self, line: &str) -> Option<Match> {
+        // the longest phrase from the leftmost start, found without looking past it
+        (0..line.len()).find_map(|start| {
+            phrases(line, start)
+                .into_iter()
+                .max_by_key(|&(end, _)| end)
+                .map(|(end, value)| Match { start, end, value })
+        })
+    }
+
+    fn last(&self, line: &str) -> Option<Match> {
+        // ascending starts so the longest phrase wins when several end at the same spot
+        let mut best: Option<Match> = None;
+        for start in 0..line.len() {
+            for (end, value) in phrases(line, start) {
+                if best.is_none_or(|b| end > b.end) {
+                    best = Some(Match { start, end, value });
+                }
+            }
+        }
+        best
+    }
+}
+
+/// the digit a number starts with
+pub fn leading_digit(mut value: u32) -> u32 {
+    while value >= 10 {
+        value /= 10;
+    }
+    value
+}
+
+/// (end, value) of every phrase or digit starting at byte offset at
+fn phrases(line: &str, at: usize) -> Vec<(usize, u32)> {
+    let byte = line.as_bytes()[at];
+    if byte.is_ascii_digit() {
+        return vec![(at + 1, u32::from(byte - b'0'))];
+    }
+
+    let mut found = below_thousand(line, at);
+    for (end, value) in found.clone() {
+        if let Some(end) = after(line, end, "thousand") {
+            found.push((end, value * 1000));
+            found.extend(
+                below_thousand(line, end)
+                    .into_iter()
+                    .map(|(e, v)| (e, value * 1000 + v)),
+            );
+        }
+    }
+    found
+}
+
+// 1 through 999
+fn below_thousand(line: &str, at: usize) -> Vec<(usize, u32)> {
+    let mut found = below_hundred(line, at);
+    for (end, value) in words(line, at, &UNITS) {
+        if let Some(end) = after(line, end, "hundred") {
+            found.push((end, value * 100));
+            found.extend(
+                below_hundred(line, end)
+                    .into_iter()
+                    .map(|(e, v)| (e, value * 100 + v)),
+            );
+        }
+    }
+    found
+}
+
+// 1 through 99
+fn below_hundred(line: &str, at: usize) -> Vec<(usize, u32)> {
+    let mut found = words(line, at, &UNITS);
+    found.extend(words(line, at, &TEENS));
+    for (end, value) in words(line, at, &TENS) {
+        found.push((end, value));
+        found.extend(
+            words(line, end, &UNITS)
+                .into_iter()
+                .map(|(e, v)| (e, value + v)),
+        );
+    }
+    found
+}
+
+// every word from the table that starts at byte offset at
+fn words(line: &str, at: usize, table: &[(&str, u32)]) -> Vec<(usize, u32)> {
+    table
+        .iter()
+        .filter(|(word, _)| line.as_bytes()[at..].starts_with(word.as_bytes()))
+        .map(|(word, value)| (at + word.len(), *value))
+        .collect()
+}
+
+// where a word ends if it comes right at byte offset at
+fn after(line: &str, at: usize, word: &str) -> Option<usize> {
+    line.as_bytes()[at..]
+        .starts_with(word.as_bytes())
+        .then_some(at + word.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_and_last(line: &str) -> Option<(u32, u32)> {
+        let first = CompoundMatcher.first(line)?;
+        let last = CompoundMatcher.last(line)?;
+        Some((first.value, last.value))
+    }
+
+    #[test]
+    fn reads_compound_numbers() {
+        assert_eq!(first_and_last("twentythree"), Some((23, 23)));
+        assert_eq!(first_and_last("ninetynine"), Some((99, 99)));
+        assert_eq!(first_and_last("onehundred"), Some((100, 100)));
+        assert_eq!(first_and_last("onehundredone"), Some((101, 101)));
+        assert_eq!(
+            first_and_last("ninehundredninetyninethousandninehundredninetynine"),
+            Some((999_999, 999_999))
+        );
+    }
+
+    #[test]
+    fn longest_phrase_wins_over_its_prefix() {
+        // eighteen starts with eight, seventy with seven
+        assert_eq!(first_and_last("eighteen"), Some((18, 18)));
+        assert_eq!(first_and_last("xseventyx"), Some((70, 70)));
+    }
+
+    #[test]
+    fn overlapping_words_still_count_separately() {
+        assert_eq!(first_and_last("eightwo"), Some((8, 2)));
+        assert_eq!(first_and_last("twentyoneight"), Some((21, 8)));
+        // the last phrase is the one ending furthest right, not starting furthest right
+        assert_eq!(first_and_last("twentyeighteen"), Some((28, 18)));
+    }
+
+    #[test]
+    fn digits_are_single_numbers() {
+        assert_eq!(first_and_last("23twenty"), Some((2, 20)));
+        assert_eq!(first_and_last("abc"), None);
+    }
+
+    #[test]
+    fn pieces_that_are_not_numbers_on_their_own() {
+        assert_eq!(first_and_last("hundredthousand"), None);
+        assert_eq!(first_and_last("twentyhundred"), Some((20, 20)));
+    }
+
+    #[test]
+    fn leading_digits() {
+        assert_eq!(leading_digit(7), 7);
+        assert_eq!(leading_digit(18), 1);
+        assert_eq!(leading_digit(999_999), 9);
+    }
+}
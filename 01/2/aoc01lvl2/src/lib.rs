@@ -1,100 +1,450 @@
-use std::fs;
-use std::io;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Stdout};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 extern crate clap;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
 extern crate anyhow;
 
-extern crate fancy_regex;
-use fancy_regex::Regex;
+extern crate aoccommon;
+use aoccommon::alloc;
+use aoccommon::batch;
+use aoccommon::config::{AocConfig, OutputFormat};
+use aoccommon::report::{Field, ReportWriter};
+
+mod compound;
+use compound::CompoundMatcher;
+
+mod matcher;
+use matcher::{Find, Match, Matcher};
+
+mod vocabulary;
+use vocabulary::{Language, Vocabulary};
 
 #[derive(Parser, Debug)]
 pub struct Args {
     /// path to the input file
     #[arg(short, long)]
     input: Option<PathBuf>,
+    /// path to a directory of input files to solve in parallel
+    #[arg(long, conflicts_with = "input")]
+    inputs: Option<PathBuf>,
+    /// skip lines without any digits instead of failing on them
+    #[arg(long)]
+    skip_invalid: bool,
+    /// which language's number words count as digits
+    #[arg(short, long, value_enum)]
+    language: Option<Language>,
+    /// file of `word = digit` lines to use as the number words instead
+    #[arg(short, long, conflicts_with = "language")]
+    words: Option<PathBuf>,
+    /// also read english number phrases like "twentythree" or "onehundred" as whole numbers
+    #[arg(long, conflicts_with_all = ["language", "words"])]
+    compound: bool,
+    /// read the input a line at a time instead of loading it all into memory
+    #[arg(long, conflicts_with = "inputs")]
+    stream: bool,
+    /// print each line's first and last match and its value instead of just the sum,
+    /// as text, json or csv (defaults to output_format from aoc.toml)
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1, conflicts_with = "inputs")]
+    report: Option<Option<OutputFormat>>,
 }
 
 enum InputConfig {
     File(PathBuf),
     Stdin,
+    Batch(PathBuf, Option<Duration>),
 }
 pub struct Config {
     input: InputConfig,
+    skip_invalid: bool,
+    matcher: Arc<dyn Find + Send + Sync>,
+    stream: bool,
+    report: Option<OutputFormat>,
 }
 
 impl Config {
-    pub fn make() -> Self {
+    pub fn make() -> anyhow::Result<Self> {
         let args = Args::parse();
-        let input = if let Some(path) = args.input {
+        let file_cfg = AocConfig::load()?;
+        // command line input wins over the config file
+        let input = if let Some(dir) = args.inputs {
+            InputConfig::Batch(dir, file_cfg.timeout())
+        } else if let Some(path) = args
+            .input
+            .map(Ok)
+            .or_else(|| file_cfg.input_path(1).transpose())
+            .transpose()?
+        {
             InputConfig::File(path)
         } else {
             InputConfig::Stdin
         };
 
-        Config { input }
+        // --compound, a word file or a language on the command line win over the config file
+        let compound = args.compound
+            || (args.language.is_none()
+                && args.words.is_none()
+                && file_cfg.day_param::<bool>(1, "compound")?.unwrap_or(false));
+        let matcher: Arc<dyn Find + Send + Sync> = if compound {
+            Arc::new(CompoundMatcher)
+        } else if let Some(path) = args.words {
+            Arc::new(Matcher::new(&Vocabulary::from_file(&path)?))
+        } else {
+            let language = match args.language {
+                Some(language) => language,
+                None => match file_cfg.day_param::<String>(1, "language")? {
+                    Some(name) => Language::from_str(&name, true)
+                        .map_err(|e| anyhow::anyhow!("invalid language in [day.1]: {}", e))?,
+                    None => Language::English,
+                },
+            };
+            Arc::new(Matcher::new(&Vocabulary::builtin(language)))
+        };
+
+        Ok(Config {
+            input,
+            skip_invalid: args.skip_invalid,
+            matcher,
+            stream: args.stream,
+            report: args
+                .report
+                .map(|format| format.unwrap_or(file_cfg.output_format)),
+        })
     }
 }
 
 pub fn run(cfg: Config) -> anyhow::Result<()> {
-    // figure out where to get our input from and read it into a string
-    let input_string = match cfg.input {
-        InputConfig::File(path) => fs::read_to_string(path)?,
-        InputConfig::Stdin => {
-            let mut buf = String::new();
-            io::stdin().read_line(&mut buf)?;
-            buf
+    let matcher = cfg.matcher.as_ref();
+    let result = if cfg.stream {
+        match cfg.input {
+            InputConfig::File(path) => stream(
+                BufReader::new(File::open(path)?),
+                cfg.skip_invalid,
+                matcher,
+                cfg.report,
+            )?,
+            InputConfig::Stdin => {
+                stream(io::stdin().lock(), cfg.skip_invalid, matcher, cfg.report)?
+            }
+            InputConfig::Batch(..) => unreachable!("clap rejects --stream with --inputs"),
         }
-    };
+    } else {
+        // figure out where to get our input from and read it into a string
+        let input_string = match cfg.input {
+            InputConfig::File(path) => fs::read_to_string(path)?,
+            InputConfig::Stdin => {
+                let mut buf = String::new();
+                io::stdin().read_line(&mut buf)?;
+                buf
+            }
+            InputConfig::Batch(dir, timeout) => {
+                let matcher = Arc::clone(&cfg.matcher);
+                let skip_invalid = cfg.skip_invalid;
+                let report = batch::run_batch(&dir, timeout, move |input| {
+                    solve(input, skip_invalid, matcher.as_ref(), None)
+                })?;
+                println!("{}", report);
+                if report.failures() > 0 {
+                    anyhow::bail!(
+                        "{} of {} inputs failed",
+                        report.failures(),
+                        report.results.len()
+                    );
+                }
+                return Ok(());
+            }
+        };
 
-    let data = parse(input_string)?;
-    let result = process(data);
+        solve(input_string, cfg.skip_invalid, matcher, cfg.report)?
+    };
 
-    println!("{}", result);
+    if result.skipped > 0 {
+        eprintln!("skipped {} lines without a digit", result.skipped);
+    }
+    // the report has stdout to itself so it can be piped straight into other tools
+    if cfg.report.is_some() {
+        eprintln!("total: {}", result.sum);
+    } else {
+        println!("{}", result.sum);
+    }
 
     Ok(())
 }
 
+/// parse and process a single input
+fn solve(
+    input: String,
+    skip_invalid: bool,
+    matcher: &dyn Find,
+    report: Option<OutputFormat>,
+) -> anyhow::Result<Total> {
+    let data = alloc::measure("parse", || parse(input))?;
+    let tally = alloc::measure("solve", || process(data, skip_invalid, matcher, report))?;
+    tally.finish()
+}
+
+/// sum the calibration values of a reader without holding more than one line in memory
+fn stream(
+    mut reader: impl BufRead,
+    skip_invalid: bool,
+    matcher: &dyn Find,
+    report: Option<OutputFormat>,
+) -> anyhow::Result<Total> {
+    let mut tally = Tally::new(report);
+    // one buffer reused for every line so memory stays flat however long the input is
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        tally.add(line.trim_end_matches(['\n', '\r']), skip_invalid, matcher)?;
+        line.clear();
+    }
+    tally.finish()
+}
+
 fn parse(input: String) -> anyhow::Result<Vec<String>> {
     Ok(input.lines().map(|l| l.to_string()).collect())
 }
 
-fn process(data: Vec<String>) -> u32 {
-    let pattern = Regex::new("(?=([0-9]|one|two|three|four|five|six|seven|eight|nine))").unwrap();
-    data.into_iter()
-        .map(|line| {
-            let mut captures = pattern
-                .captures_iter(&line)
-                .map(|c| c.expect("bad input: unable to find any numbers"))
-                .map(|c| c.get(1));
-
-            let first = captures
-                .next()
-                .expect("bad input: unable to find any numbers")
-                .expect("bad input: unable to find any numbers")
-                .as_str();
-            let last = match captures.last() {
-                Some(s) => s.expect("bad input: unable to find any numbers").as_str(),
-                None => first,
-            };
-            convert_match_to_digit(first) * 10 + convert_match_to_digit(last)
+/// add up every line's calibration value
+fn process(
+    data: Vec<String>,
+    skip_invalid: bool,
+    matcher: &dyn Find,
+    report: Option<OutputFormat>,
+) -> anyhow::Result<Tally> {
+    let mut tally = Tally::new(report);
+    for line in data.iter() {
+        tally.add(line, skip_invalid, matcher)?;
+    }
+    Ok(tally)
+}
+
+const REPORT_COLUMNS: &[&str] = &[
+    "line",
+    "first",
+    "first_start",
+    "first_end",
+    "last",
+    "last_start",
+    "last_end",
+    "value",
+];
+
+/// running total of calibration values, fed one line at a time
+struct Tally {
+    // u64 so even billions of lines of 99s can't overflow
+    sum: u64,
+    lines: usize,
+    skipped: usize,
+    report: Option<ReportWriter<BufWriter<Stdout>>>,
+}
+
+impl Tally {
+    fn new(report: Option<OutputFormat>) -> Self {
+        Tally {
+            sum: 0,
+            lines: 0,
+            skipped: 0,
+            report: report.map(|format| {
+                ReportWriter::new(BufWriter::new(io::stdout()), format, REPORT_COLUMNS)
+            }),
+        }
+    }
+
+    fn add(&mut self, line: &str, skip_invalid: bool, matcher: &dyn Find) -> anyhow::Result<()> {
+        self.lines += 1;
+        let found = first_and_last(line, matcher);
+        match found {
+            Some((first, last)) => self.sum += u64::from(calibration_value(&first, &last)),
+            None if skip_invalid => self.skipped += 1,
+            None => anyhow::bail!(
+                "line {}: no digit or number word found in {:?}",
+                self.lines,
+                line
+            ),
+        }
+
+        if let Some(report) = &mut self.report {
+            report.row(report_row(self.lines, line, found))?;
+        }
+        Ok(())
+    }
+
+    /// close off the report and hand back the sum
+    fn finish(self) -> anyhow::Result<Total> {
+        if let Some(report) = self.report {
+            report.finish()?;
+        }
+        Ok(Total {
+            sum: self.sum,
+            skipped: self.skipped,
         })
-        .sum()
-}
-
-fn convert_match_to_digit(m: &str) -> u32 {
-    match m {
-        "one" => 1,
-        "two" => 2,
-        "three" => 3,
-        "four" => 4,
-        "five" => 5,
-        "six" => 6,
-        "seven" => 7,
-        "eight" => 8,
-        "nine" => 9,
-        other => other.parse().unwrap(),
+    }
+}
+
+/// a line's report row, in the order of REPORT_COLUMNS
+fn report_row(number: usize, line: &str, found: Option<(Match, Match)>) -> Vec<Field> {
+    let (first, last) = found.unzip();
+    let text = |m: Match| &line[m.start..m.end];
+    vec![
+        number.into(),
+        first.map(text).into(),
+        first.map(|m| m.start).into(),
+        first.map(|m| m.end).into(),
+        last.map(text).into(),
+        last.map(|m| m.start).into(),
+        last.map(|m| m.end).into(),
+        found
+            .map(|(first, last)| calibration_value(&first, &last))
+            .into(),
+    ]
+}
+
+/// the sum of an input and how many lines it had to skip to get there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Total {
+    sum: u64,
+    skipped: usize,
+}
+
+/// the sum, and the skipped lines if there were any, so each file of a batch says how many
+/// of its own lines it skipped
+impl fmt::Display for Total {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.sum)?;
+        if self.skipped > 0 {
+            write!(f, ", skipped {} lines without a digit", self.skipped)?;
+        }
+        Ok(())
+    }
+}
+
+/// the first and last number on a line, if there are any numbers
+fn first_and_last(line: &str, matcher: &dyn Find) -> Option<(Match, Match)> {
+    Some((matcher.first(line)?, matcher.last(line)?))
+}
+
+/// the first and last number as a two digit number
+///
+/// compound numbers count by their leading digit at the start of the line and their last
+/// digit at the end, so "twentythree" is 23 and "onehundred" is 10
+fn calibration_value(first: &Match, last: &Match) -> u32 {
+    compound::leading_digit(first.value) * 10 + last.value % 10
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total(input: &str, skip_invalid: bool) -> anyhow::Result<Total> {
+        let matcher = Matcher::new(&Vocabulary::builtin(Language::English));
+        solve(input.to_string(), skip_invalid, &matcher, None)
+    }
+
+    #[test]
+    fn example() {
+        let input = "two1nine\neightwothree\nabcone2threexyz\nxtwone3four\n\
+                     4nineeightseven2\nzoneight234\n7pqrstsixteen\n";
+        assert_eq!(total(input, false).unwrap().sum, 281);
+    }
+
+    #[test]
+    fn lines_without_a_number_say_which_line() {
+        let err = total("two1nine\nxyz\n3\n", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: no digit or number word found in \"xyz\""
+        );
+    }
+
+    #[test]
+    fn skipped_lines_are_counted() {
+        let result = total("two1nine\nxyz\n\nthree\nnope", true).unwrap();
+        assert_eq!(
+            result,
+            Total {
+                sum: 62,
+                skipped: 3
+            }
+        );
+        assert_eq!(result.to_string(), "62, skipped 3 lines without a digit");
+    }
+
+    #[test]
+    fn a_final_newline_is_not_a_line() {
+        assert_eq!(total("two1nine\n", false).unwrap().sum, 29);
+        let err = total("two1nine\n\n", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: no digit or number word found in \"\""
+        );
+        assert_eq!(total("two1nine\n\n", true).unwrap().skipped, 1);
+    }
+
+    #[test]
+    fn streaming_matches_reading_it_all() {
+        let matcher = Matcher::new(&Vocabulary::builtin(Language::English));
+        for input in [
+            "two1nine\neightwothree\nabcone2threexyz\n",
+            "two1nine\r\neightwothree\r\nxyz\r\n",
+            "two1nine\neightwothree",
+            "eightwo\r\n\r\noneight",
+            "",
+        ] {
+            let streamed = stream(input.as_bytes(), true, &matcher, None).unwrap();
+            assert_eq!(streamed, total(input, true).unwrap(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn streaming_errors_match_too() {
+        let matcher = Matcher::new(&Vocabulary::builtin(Language::English));
+        let input = "1\r\nxyz\r\n";
+        let streamed = stream(input.as_bytes(), false, &matcher, None).unwrap_err();
+        assert_eq!(
+            streamed.to_string(),
+            total(input, false).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn report_rows_give_byte_offsets() {
+        let matcher = Matcher::new(&Vocabulary::builtin(Language::English));
+        let line = "eightwothree";
+        let row = report_row(2, line, first_and_last(line, &matcher));
+        assert_eq!(
+            row,
+            vec![
+                Field::Number(2),
+                Field::Text("eight".to_string()),
+                Field::Number(0),
+                Field::Number(5),
+                Field::Text("three".to_string()),
+                Field::Number(7),
+                Field::Number(12),
+                Field::Number(83),
+            ]
+        );
+        // offsets count bytes, not characters
+        let matcher = Matcher::new(&Vocabulary::builtin(Language::German));
+        let line = "ü3fünf";
+        let row = report_row(1, line, first_and_last(line, &matcher));
+        assert_eq!(row[2..4], [Field::Number(2), Field::Number(3)]);
+        assert_eq!(
+            row[5..],
+            [Field::Number(3), Field::Number(8), Field::Number(35)]
+        );
+    }
+
+    #[test]
+    fn report_rows_for_skipped_lines_are_empty() {
+        let matcher = Matcher::new(&Vocabulary::builtin(Language::English));
+        let row = report_row(4, "xyz", first_and_last("xyz", &matcher));
+        assert_eq!(row[0], Field::Number(4));
+        assert!(row[1..].iter().all(|field| *field == Field::Empty));
     }
 }
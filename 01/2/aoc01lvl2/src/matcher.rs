@@ -0,0 +1,252 @@
+//! finds the first and last vocabulary word on a line in linear time
+//!
+//! this is an aho-corasick automaton over bytes. the first match comes from scanning forward,
+//! the last from scanning the line backward with an automaton built from the reversed words,
+//! so overlapping words like "eightwo" are found without any backtracking
+
+use std::collections::VecDeque;
+
+use crate::vocabulary::Vocabulary;
+
+/// a word found on a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    /// byte offset of the first byte of the word
+    pub start: usize,
+    /// byte offset just past the word
+    pub end: usize,
+    /// the number the word stands for, a single digit unless it's a compound word
+    pub value: u32,
+}
+
+/// something that can pick out the first and last number on a line
+pub trait Find {
+    fn first(&self, line: &str) -> Option<Match>;
+    fn last(&self, line: &str) -> Option<Match>;
+}
+
+pub struct Matcher {
+    forward: Automaton,
+    backward: Automaton,
+    // length in bytes of the longest word, bounds how far the forward scan has to look
+    max_len: usize,
+}
+
+impl Matcher {
+    pub fn new(vocabulary: &Vocabulary) -> Self {
+        let words = vocabulary.entries().collect::<Vec<_>>();
+        let reversed = words
+            .iter()
+            .map(|(word, value)| (word.bytes().rev().collect::<Vec<_>>(), *value))
+            .collect::<Vec<_>>();
+
+        Matcher {
+            forward: Automaton::new(words.iter().map(|(word, value)| (word.as_bytes(), *value))),
+            backward: Automaton::new(
+                reversed
+                    .iter()
+                    .map(|(word, value)| (word.as_slice(), *value)),
+            ),
+            max_len: words.iter().map(|(word, _)| word.len()).max().unwrap_or(0),
+        }
+    }
+}
+
+impl Find for Matcher {
+    /// the word starting furthest left, taking the longest if several start there
+    fn first(&self, line: &str) -> Option<Match> {
+        let mut best: Option<Match> = None;
+        let mut state = 0;
+        for (i, &byte) in line.as_bytes().iter().enumerate() {
+            let end = i + 1;
+            // nothing ending this late can start at or before the best start we have
+            if let Some(best) = best {
+                if end > best.start + self.max_len {
+                    break;
+                }
+            }
+
+            state = self.forward.step(state, byte);
+            for &(len, value) in self.forward.outputs(state) {
+                let found = Match {
+                    start: end - len,
+                    end,
+                    value,
+                };
+                best = match best {
+                    Some(b)
+                        if b.start < found.start
+                            || (b.start == found.start && b.end >= found.end) =>
+                    {
+                        Some(b)
+                    }
+                    _ => Some(found),
+                };
+            }
+        }
+        best
+    }
+
+    /// the word starting furthest right, taking the longest if several start there
+    fn last(&self, line: &str) -> Option<Match> {
+        let bytes = line.as_bytes();
+        let mut state = 0;
+        for (i, &byte) in bytes.iter().enumerate().rev() {
+            state = self.backward.step(state, byte);
+            // everything found at this step starts at i, so the first step with a match wins
+            if let Some(&(len, value)) = self.backward.outputs(state).iter().max() {
+                return Some(Match {
+                    start: i,
+                    end: i + len,
+                    value,
+                });
+            }
+        }
+        None
+    }
+}
+
+/// a dense aho-corasick automaton with the failure links already folded into the transitions
+struct Automaton {
+    states: Vec<State>,
+}
+
+struct State {
+    next: [u32; 256],
+    /// (length, value) of every word ending at this state, including through failure links
+    outputs: Vec<(usize, u32)>,
+}
+
+impl State {
+    fn new() -> Self {
+        State {
+            next: [0; 256],
+            outputs: Vec::new(),
+        }
+    }
+}
+
+impl Automaton {
+    fn new<'w>(words: impl Iterator<Item = (&'w [u8], u32)>) -> Self {
+        // build the trie, using 0 for missing edges since the root can't be a child
+        let mut states = vec![State::new()];
+        for (word, value) in words {
+            let mut cur = 0;
+            for &byte in word {
+                cur = match states[cur].next[byte as usize] {
+                    0 => {
+                        states.push(State::new());
+                        let new = states.len() - 1;
+                        states[cur].next[byte as usize] = new as u32;
+                        new
+                    }
+                    existing => existing as usize,
+                };
+            }
+            if !word.is_empty() {
+                states[cur].outputs.push((word.len(), value));
+            }
+        }
+
+        // breadth first, point each missing edge at wherever the failure state would go
+        // and inherit the failure state's outputs
+        let mut fail = vec![0; states.len()];
+        let mut queue = VecDeque::new();
+        for byte in 0..256 {
+            let child = states[0].next[byte] as usize;
+            if child != 0 {
+                queue.push_back(child);
+            }
+        }
+        while let Some(cur) = queue.pop_front() {
+            let inherited = states[fail[cur]].outputs.clone();
+            states[cur].outputs.extend(inherited);
+            for byte in 0..256 {
+                let child = states[cur].next[byte] as usize;
+                let fallback = states[fail[cur]].next[byte];
+                if child == 0 {
+                    states[cur].next[byte] = fallback;
+                } else {
+                    fail[child] = fallback as usize;
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        Automaton { states }
+    }
+
+    fn step(&self, state: usize, byte: u8) -> usize {
+        self.states[state].next[byte as usize] as usize
+    }
+
+    fn outputs(&self, state: usize) -> &[(usize, u32)] {
+        &self.states[state].outputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vocabulary::Language;
+
+    fn english() -> Matcher {
+        Matcher::new(&Vocabulary::builtin(Language::English))
+    }
+
+    fn found(start: usize, end: usize, value: u32) -> Option<Match> {
+        Some(Match { start, end, value })
+    }
+
+    #[test]
+    fn overlapping_words_both_count() {
+        let matcher = english();
+        assert_eq!(matcher.first("eightwo"), found(0, 5, 8));
+        assert_eq!(matcher.last("eightwo"), found(4, 7, 2));
+        assert_eq!(matcher.first("oneight"), found(0, 3, 1));
+        assert_eq!(matcher.last("oneight"), found(2, 7, 8));
+        assert_eq!(matcher.last("twoneight"), found(4, 9, 8));
+    }
+
+    #[test]
+    fn the_longest_word_wins_where_several_start() {
+        // "se" and "seven", "ven" and "seven": prefixes and suffixes of another word
+        let table = "se = 2\nseven = 7\nven = 5\n";
+        let matcher = Matcher::new(&Vocabulary::from_table(table).unwrap());
+        assert_eq!(matcher.first("xseven"), found(1, 6, 7));
+        assert_eq!(matcher.last("xseven"), found(3, 6, 5));
+        assert_eq!(matcher.first("sevxn"), found(0, 2, 2));
+        // a later, longer word doesn't beat one that starts earlier
+        assert_eq!(matcher.first("seseven"), found(0, 2, 2));
+        assert_eq!(matcher.last("seven."), found(2, 5, 5));
+    }
+
+    #[test]
+    fn offsets_are_bytes_in_multi_byte_words() {
+        let matcher = Matcher::new(&Vocabulary::builtin(Language::German));
+        // ü is two bytes
+        assert_eq!(matcher.first("xfünfy"), found(1, 6, 5));
+        assert_eq!(matcher.last("xfünfy"), found(1, 6, 5));
+        assert_eq!(matcher.first("ü3fünf"), found(2, 3, 3));
+        assert_eq!(matcher.last("fünf3ü"), found(5, 6, 3));
+        assert_eq!(matcher.first("funf"), None);
+    }
+
+    #[test]
+    fn lines_without_a_word_have_no_match() {
+        let matcher = english();
+        assert_eq!(matcher.first("abcdefg"), None);
+        assert_eq!(matcher.last("abcdefg"), None);
+        assert_eq!(matcher.first("on e tw o"), None);
+        assert_eq!(matcher.first(""), None);
+        assert_eq!(matcher.last(""), None);
+    }
+
+    #[test]
+    fn digits_are_words_too() {
+        let matcher = english();
+        assert_eq!(matcher.first("7pqrstsixteen"), found(0, 1, 7));
+        assert_eq!(matcher.last("7pqrstsixteen"), found(6, 9, 6));
+        assert_eq!(matcher.first("0"), found(0, 1, 0));
+    }
+}
@@ -0,0 +1,224 @@
+//! the words that count as digits on a calibration line
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use aoccommon::parsing;
+
+/// languages we have number words for out of the box
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Language {
+    English,
+    German,
+    Spanish,
+    French,
+}
+
+impl Language {
+    /// the words for one through nine
+    fn words(self) -> [&'static str; 9] {
+        match self {
+            Language::English => [
+                "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+            ],
+            Language::German => [
+                "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+            ],
+            Language::Spanish => [
+                "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve",
+            ],
+            Language::French => [
+                "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
+            ],
+        }
+    }
+}
+
+/// everything that can be matched on a line (the digits plus number words) and the digit it stands for
+#[derive(Debug, Clone)]
+pub struct Vocabulary {
+    values: HashMap<String, u32>,
+}
+
+impl Vocabulary {
+    /// the digits plus one of the built in word lists
+    pub fn builtin(language: Language) -> Self {
+        let mut vocabulary = Vocabulary::digits();
+        for (word, value) in language.words().into_iter().zip(1..) {
+            vocabulary.values.insert(word.to_string(), value);
+        }
+        vocabulary
+    }
+
+    /// the digits plus words read from a file of `word = digit` lines
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read {}: {}", path.display(), e))?;
+        Vocabulary::from_table(&contents).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+    }
+
+    /// the digits plus words from `word = digit` lines, ignoring blank lines and # comments
+    pub fn from_table(table: &str) -> parsing::Result<Self> {
+        let mut vocabulary = Vocabulary::digits();
+        for line in parsing::lines(table) {
+            if line.trim().is_empty() || line.trim().as_str().starts_with('#') {
+                continue;
+            }
+
+            let (word, digit) = line.split_once("=")?;
+            let word = word.trim();
+            if word.is_empty() {
+                return Err(word.error("missing word before '='"));
+            }
+            let value = digit.parse::<u32>("an unsigned integer")?;
+            if value > 9 {
+                return Err(digit
+                    .trim()
+                    .error(format!("{} is not a single digit", value)));
+            }
+            if let Some(old) = vocabulary.values.insert(word.as_str().to_string(), value) {
+                if old != value {
+                    return Err(word.error(format!(
+                        "{:?} is already listed as {}",
+                        word.as_str(),
+                        old
+                    )));
+                }
+            }
+        }
+        Ok(vocabulary)
+    }
+
+    /// every word along with the digit it stands for, in no particular order
+    pub fn entries(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.values
+            .iter()
+            .map(|(word, &value)| (word.as_str(), value))
+    }
+
+    // just 0 through 9
+    fn digits() -> Self {
+        Vocabulary {
+            values: (0..=9).map(|d| (d.to_string(), d)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::{Find, Matcher};
+
+    /// the digits of the first and last word on a line
+    fn ends(vocabulary: &Vocabulary, line: &str) -> (u32, u32) {
+        let matcher = Matcher::new(vocabulary);
+        (
+            matcher.first(line).unwrap().value,
+            matcher.last(line).unwrap().value,
+        )
+    }
+
+    fn table_error(table: &str) -> (usize, usize, String) {
+        let err = Vocabulary::from_table(table).unwrap_err();
+        (err.line, err.col, err.msg)
+    }
+
+    #[test]
+    fn english_words_overlap() {
+        let english = Vocabulary::builtin(Language::English);
+        assert_eq!(ends(&english, "eightwo"), (8, 2));
+        assert_eq!(ends(&english, "oneight"), (1, 8));
+        assert_eq!(ends(&english, "xtwone3four"), (2, 4));
+        assert_eq!(ends(&english, "sevenine"), (7, 9));
+    }
+
+    #[test]
+    fn german_words_overlap() {
+        let german = Vocabulary::builtin(Language::German);
+        assert_eq!(ends(&german, "sechsieben"), (6, 7));
+        assert_eq!(ends(&german, "einsieben"), (1, 7));
+        assert_eq!(ends(&german, "fünfünf"), (5, 5));
+        assert_eq!(ends(&german, "xneunacht"), (9, 8));
+    }
+
+    #[test]
+    fn spanish_words_overlap() {
+        let spanish = Vocabulary::builtin(Language::Spanish);
+        assert_eq!(ends(&spanish, "doseis"), (2, 6));
+        assert_eq!(ends(&spanish, "tresiete"), (3, 7));
+        assert_eq!(ends(&spanish, "unocho"), (1, 8));
+        assert_eq!(ends(&spanish, "cincocho"), (5, 8));
+    }
+
+    #[test]
+    fn french_words_overlap() {
+        let french = Vocabulary::builtin(Language::French);
+        assert_eq!(ends(&french, "huitrois"), (8, 3));
+        assert_eq!(ends(&french, "septrois"), (7, 3));
+        // un hides inside ordinary words, and still counts there
+        assert_eq!(ends(&french, "aucun7"), (1, 7));
+        assert_eq!(ends(&french, "4lundi"), (4, 1));
+        assert_eq!(ends(&french, "deuxunneuf"), (2, 9));
+    }
+
+    #[test]
+    fn languages_only_know_their_own_words() {
+        let matcher = Matcher::new(&Vocabulary::builtin(Language::French));
+        assert_eq!(matcher.first("onetwothree"), None);
+        let matcher = Matcher::new(&Vocabulary::builtin(Language::English));
+        assert_eq!(matcher.first("deuxtrois"), None);
+    }
+
+    #[test]
+    fn tables_add_to_the_digits() {
+        let table = "# roman numerals\n\ni = 1\nv=5\n  ix = 9  \ni = 1\n";
+        let roman = Vocabulary::from_table(table).unwrap();
+        assert_eq!(roman.entries().count(), 13);
+        assert_eq!(ends(&roman, "xiv"), (1, 5));
+        assert_eq!(ends(&roman, "x7ix"), (7, 9));
+    }
+
+    #[test]
+    fn a_line_without_equals_is_an_error() {
+        assert_eq!(
+            table_error("one = 1\ntwo 2\n"),
+            (2, 1, "expected \"=\" in \"two 2\"".to_string())
+        );
+        assert_eq!(
+            table_error("= 1"),
+            (1, 1, "missing word before '='".to_string())
+        );
+    }
+
+    #[test]
+    fn values_have_to_be_single_digits() {
+        assert_eq!(
+            table_error("one = 1\n\nten =  10"),
+            (3, 8, "10 is not a single digit".to_string())
+        );
+        assert_eq!(
+            table_error("one = uno"),
+            (
+                1,
+                7,
+                "expected an unsigned integer, found \"uno\"".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn conflicting_duplicates_are_errors() {
+        assert_eq!(
+            table_error("one = 1\n  one = 2"),
+            (2, 3, "\"one\" is already listed as 1".to_string())
+        );
+        // the digits themselves are listed too
+        assert_eq!(
+            table_error("7 = 1"),
+            (1, 1, "\"7\" is already listed as 7".to_string())
+        );
+    }
+}
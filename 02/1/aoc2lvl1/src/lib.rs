@@ -1,37 +1,78 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
 extern crate clap;
 use clap::Parser;
 
 extern crate anyhow;
 
+extern crate aoccommon;
+use aoccommon::alloc;
+use aoccommon::batch;
+use aoccommon::config::AocConfig;
+use aoccommon::parsing::{self, Span};
+
 #[derive(Parser, Debug)]
 pub struct Args {
     /// path to the input file
     #[arg(short, long)]
     input: Option<PathBuf>,
+    /// path to a directory of input files to solve in parallel
+    #[arg(long, conflicts_with = "input")]
+    inputs: Option<PathBuf>,
+    /// most cubes of a color the bag holds, like `red=12` (repeat for each color)
+    #[arg(short, long = "limit", value_name = "COLOR=N", value_parser = parse_limit)]
+    limits: Vec<(String, u32)>,
+    /// list the possible games and what rules out each impossible one instead of summing ids
+    #[arg(short, long)]
+    query: bool,
 }
 
 enum InputConfig {
     File(PathBuf),
     Stdin,
+    Batch(PathBuf, Option<Duration>),
 }
 pub struct Config {
     input: InputConfig,
+    limits: BagLimits,
+    query: bool,
 }
 
 impl Config {
-    pub fn make() -> Self {
+    pub fn make() -> anyhow::Result<Self> {
         let args = Args::parse();
-        let input = if let Some(path) = args.input {
+        let file_cfg = AocConfig::load()?;
+        // command line input wins over the config file
+        let input = if let Some(dir) = args.inputs {
+            InputConfig::Batch(dir, file_cfg.timeout())
+        } else if let Some(path) = args
+            .input
+            .map(Ok)
+            .or_else(|| file_cfg.input_path(2).transpose())
+            .transpose()?
+        {
             InputConfig::File(path)
         } else {
             InputConfig::Stdin
         };
 
-        Config { input }
+        // command line limits win over the config file, which wins over the defaults
+        let mut limits = BagLimits::default();
+        if let Some(table) = file_cfg.day_param::<HashMap<String, u32>>(2, "limits")? {
+            limits.extend(table);
+        }
+        limits.extend(args.limits);
+
+        Ok(Config {
+            input,
+            limits,
+            query: args.query,
+        })
     }
 }
 
@@ -44,86 +85,255 @@ pub fn run(cfg: Config) -> anyhow::Result<()> {
             io::stdin().read_line(&mut buf)?;
             buf
         }
+        InputConfig::Batch(dir, timeout) => {
+            let report = batch::run_batch(&dir, timeout, move |input| {
+                solve(input, &cfg.limits, cfg.query)
+            })?;
+            println!("{}", report);
+            if report.failures() > 0 {
+                anyhow::bail!(
+                    "{} of {} inputs failed",
+                    report.failures(),
+                    report.results.len()
+                );
+            }
+            return Ok(());
+        }
     };
 
-    let data = parse(input_string)?;
-    let result = process(data);
+    let result = solve(input_string, &cfg.limits, cfg.query)?;
 
     println!("{}", result);
 
     Ok(())
 }
 
-/// parse game strings into games containing samples of optional color counts
+/// parse and process a single input, or describe which games are possible in query mode
+fn solve(input: String, limits: &BagLimits, query: bool) -> anyhow::Result<String> {
+    let data = alloc::measure("parse", || parse(input))?;
+    if query {
+        Ok(alloc::measure("query", || possible_games(&data, limits)).to_string())
+    } else {
+        Ok(alloc::measure("solve", || process(data, limits)).to_string())
+    }
+}
+
+// `red=12` from the command line
+fn parse_limit(arg: &str) -> Result<(String, u32), String> {
+    let (color, max) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected COLOR=N, found {:?}", arg))?;
+    let max = max
+        .trim()
+        .parse()
+        .map_err(|_| format!("expected a cube count after '=', found {:?}", max))?;
+    // colors in the input are lowercased as they're parsed, so limits have to be too
+    Ok((color.trim().to_lowercase(), max))
+}
+
+/// labels a game record can start with, matched ignoring case
+const LABELS: [&str; 2] = ["game", "round"];
+
+/// parse game strings into games containing samples of color counts
+///
+/// the grammar is forgiving: labels and colors can be in any case, whitespace can vary,
+/// blank lines and empty draws are skipped, and a count can run straight into its color
 fn parse(input: String) -> anyhow::Result<Vec<Game>> {
     let mut games = Vec::new();
+    let mut seen = HashSet::new();
 
-    for line in input.lines() {
-        let game_label_end = line.find(':').expect("invalid input, missing colon");
-        let id = line[5..game_label_end].parse::<u32>()?;
+    for line in parsing::lines(&input) {
+        if line.trim().is_empty() {
+            continue;
+        }
 
-        let mut cur_game = Game {
-            id,
-            samples: Vec::new(),
-        };
+        let game = parse_game(line)?;
+        if !seen.insert(game.id) {
+            return Err(line
+                .trim()
+                .error(format!("game {} is listed more than once", game.id))
+                .into());
+        }
+        games.push(game);
+    }
 
-        for sample_str in line[game_label_end + 2..].split("; ") {
-            let (mut red, mut green, mut blue) = (None, None, None);
-            for cube_sample in sample_str.split(", ") {
-                let mut iter = cube_sample.split(' ');
-                let count = iter
-                    .next()
-                    .expect("missing cube count in sample")
-                    .parse::<u32>()
-                    .expect("bad cube count");
-                let color = iter.next().expect("missing cube color in sample");
-
-                match color {
-                    "red" => red = Some(count),
-                    "green" => green = Some(count),
-                    "blue" => blue = Some(count),
-                    _ => panic!("bad cube color in sample"),
-                };
-
-                cur_game.samples.push(Sample { red, green, blue });
+    Ok(games)
+}
+
+/// `Game 12: 3 blue, 4 red; 2 green`
+fn parse_game(line: Span<'_>) -> parsing::Result<Game> {
+    let (head, draws) = line.split_once(":")?;
+
+    let head = head.trim();
+    let label_len = head
+        .as_str()
+        .find(|c: char| !c.is_alphabetic())
+        .unwrap_or(head.as_str().len());
+    let label = head.slice(0, label_len);
+    if !LABELS
+        .iter()
+        .any(|l| label.as_str().eq_ignore_ascii_case(l))
+    {
+        return Err(label.error(format!(
+            "expected \"Game\" or \"Round\", found {:?}",
+            head.as_str()
+        )));
+    }
+    let id = head
+        .slice(label_len, head.as_str().len())
+        .parse::<u32>("an unsigned integer")?;
+
+    let mut game = Game {
+        id,
+        samples: Vec::new(),
+    };
+    for draw in draws.list(";").filter(|draw| !draw.is_empty()) {
+        let mut counts = Vec::new();
+        for cube in draw.list(",").filter(|cube| !cube.is_empty()) {
+            let (count, color) = parse_cubes(cube)?;
+            let name = color.as_str().to_lowercase();
+            if counts.iter().any(|(c, _)| *c == name) {
+                return Err(color.error(format!("{} is listed twice in one draw", name)));
             }
+            counts.push((name, count));
         }
+        game.samples.push(Sample { counts });
+    }
+
+    Ok(game)
+}
 
-        games.push(cur_game);
+/// `3 blue` (or `3blue`), giving back the count and the lowercased color
+fn parse_cubes(cube: Span<'_>) -> parsing::Result<(u32, Span<'_>)> {
+    let text = cube.as_str();
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    if digits == 0 {
+        return Err(cube.error(format!("expected a cube count, found {:?}", text)));
     }
+    let count = cube.slice(0, digits).parse::<u32>("an unsigned integer")?;
 
-    Ok(games)
+    let mut words = cube.slice(digits, text.len()).words();
+    let color = words
+        .next()
+        .ok_or_else(|| cube.error(format!("missing cube color after {}", count)))?;
+    if let Some(extra) = words.next() {
+        return Err(extra.error(format!(
+            "unexpected {:?} after the cube color",
+            extra.as_str()
+        )));
+    }
+
+    Ok((count, color))
 }
 
 /// process game structs to get our answer
-fn process(games: Vec<Game>) -> u32 {
-    const MAX_RED: u32 = 12;
-    const MAX_GREEN: u32 = 13;
-    const MAX_BLUE: u32 = 14;
-
-    let mut sum_of_possible_game_ids = 0;
-    'game_loop: for game in games {
-        for sample in game.samples {
-            if let Some(red) = sample.red {
-                if red > MAX_RED {
-                    continue 'game_loop;
-                }
-            }
-            if let Some(green) = sample.green {
-                if green > MAX_GREEN {
-                    continue 'game_loop;
-                }
-            }
-            if let Some(blue) = sample.blue {
-                if blue > MAX_BLUE {
-                    continue 'game_loop;
-                }
-            }
+fn process(games: Vec<Game>, limits: &BagLimits) -> u32 {
+    games
+        .iter()
+        .filter(|game| first_violation(game, limits).is_none())
+        .map(|game| game.id)
+        .sum()
+}
+
+/// sort the games into possible ones and impossible ones along with why
+fn possible_games<'g>(games: &'g [Game], limits: &BagLimits) -> GameQuery<'g> {
+    let mut query = GameQuery {
+        possible: Vec::new(),
+        impossible: Vec::new(),
+    };
+    for game in games {
+        match first_violation(game, limits) {
+            None => query.possible.push(game.id),
+            Some(violation) => query.impossible.push((game.id, violation)),
         }
-        sum_of_possible_game_ids += game.id;
     }
+    query
+}
 
-    sum_of_possible_game_ids
+/// the first sample (and color within it) showing more cubes than the bag can hold
+fn first_violation<'g>(game: &'g Game, limits: &BagLimits) -> Option<Violation<'g>> {
+    game.draws().enumerate().find_map(|(i, sample)| {
+        sample.counts.iter().find_map(|(color, count)| {
+            let limit = limits.get(color);
+            (*count > limit).then_some(Violation {
+                sample: i + 1,
+                color,
+                count: *count,
+                limit,
+            })
+        })
+    })
+}
+
+/// why a game couldn't have been played with the bag
+#[derive(Debug)]
+struct Violation<'g> {
+    /// 1 based
+    sample: usize,
+    color: &'g str,
+    count: u32,
+    limit: u32,
+}
+
+/// which games are possible and what rules out the rest
+#[derive(Debug)]
+struct GameQuery<'g> {
+    possible: Vec<u32>,
+    impossible: Vec<(u32, Violation<'g>)>,
+}
+
+impl fmt::Display for GameQuery<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids = self
+            .possible
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "possible games: {}", ids.join(", "))?;
+        for (id, v) in &self.impossible {
+            write!(
+                f,
+                "\ngame {}: sample {} shows {} {}, the bag only holds {}",
+                id, v.sample, v.count, v.color, v.limit
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// the most cubes of each color the bag can hold
+#[derive(Debug)]
+struct BagLimits {
+    max: HashMap<String, u32>,
+}
+
+impl BagLimits {
+    /// the limit for a color, a color without one isn't in the bag at all
+    fn get(&self, color: &str) -> u32 {
+        self.max.get(color).copied().unwrap_or(0)
+    }
+
+    /// set limits for some colors, matching them up with the input whatever their case
+    fn extend(&mut self, limits: impl IntoIterator<Item = (String, u32)>) {
+        self.max.extend(
+            limits
+                .into_iter()
+                .map(|(color, max)| (color.to_lowercase(), max)),
+        );
+    }
+}
+
+impl Default for BagLimits {
+    fn default() -> Self {
+        BagLimits {
+            max: [("red", 12), ("green", 13), ("blue", 14)]
+                .into_iter()
+                .map(|(color, max)| (color.to_string(), max))
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -132,9 +342,154 @@ struct Game {
     samples: Vec<Sample>,
 }
 
+impl Game {
+    /// every handful of cubes shown during the game, in order
+    fn draws(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+}
+
+/// one handful of cubes, everything between a pair of semicolons
 #[derive(Debug)]
 struct Sample {
-    red: Option<u32>,
-    green: Option<u32>,
-    blue: Option<u32>,
+    /// how many cubes of each color were shown, in the order they were listed
+    counts: Vec<(String, u32)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green
+";
+
+    #[test]
+    fn one_sample_per_draw() {
+        let games = parse(EXAMPLE.to_string()).unwrap();
+        let draws = games.iter().map(|g| g.draws().count()).collect::<Vec<_>>();
+        assert_eq!(draws, vec![3, 3, 3, 3, 2]);
+
+        let counts = games[0]
+            .draws()
+            .map(|d| d.counts.clone())
+            .collect::<Vec<_>>();
+        let owned = |pairs: &[(&str, u32)]| {
+            pairs
+                .iter()
+                .map(|(color, n)| (color.to_string(), *n))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            counts,
+            vec![
+                owned(&[("blue", 3), ("red", 4)]),
+                owned(&[("red", 1), ("green", 2), ("blue", 6)]),
+                owned(&[("green", 2)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn limits_ignore_case() {
+        assert_eq!(parse_limit(" RED = 1"), Ok(("red".to_string(), 1)));
+        assert!(parse_limit("red").is_err());
+        assert!(parse_limit("red=lots").is_err());
+
+        // the way a [day.2] limits table from aoc.toml is merged in
+        let mut limits = BagLimits::default();
+        limits.extend([("Green".to_string(), 1), ("BLUE".to_string(), 2)]);
+        assert_eq!(limits.get("green"), 1);
+        assert_eq!(limits.get("blue"), 2);
+        assert_eq!(limits.get("red"), 12);
+
+        let mut limits = BagLimits::default();
+        limits.extend([parse_limit("RED=1").unwrap()]);
+        let games = parse(EXAMPLE.to_string()).unwrap();
+        // only game 2 gets by on one red, ignoring the limit would leave the answer at 8
+        assert_eq!(process(games, &limits), 2);
+    }
+
+    #[test]
+    fn violations_point_at_the_draw() {
+        let games = parse(EXAMPLE.to_string()).unwrap();
+        let limits = BagLimits::default();
+        let v = first_violation(&games[2], &limits).unwrap();
+        assert_eq!((v.sample, v.color, v.count), (1, "red", 20));
+        let v = first_violation(&games[3], &limits).unwrap();
+        assert_eq!((v.sample, v.color, v.count), (3, "blue", 15));
+        assert!(first_violation(&games[0], &limits).is_none());
+    }
+
+    #[test]
+    fn tolerant_records() {
+        let input = "\
+  GAME   7 :3 Blue ,4red;;  2 GREEN ;
+
+round 8:
+";
+        let games = parse(input.to_string()).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].id, 7);
+        let counts = games[0]
+            .draws()
+            .map(|d| d.counts.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            counts,
+            vec![
+                vec![("blue".to_string(), 3), ("red".to_string(), 4)],
+                vec![("green".to_string(), 2)],
+            ]
+        );
+        assert_eq!(games[1].id, 8);
+        assert_eq!(games[1].draws().count(), 0);
+    }
+
+    #[test]
+    fn malformed_records_point_at_the_problem() {
+        let error = |input: &str| parse(input.to_string()).unwrap_err().to_string();
+        assert_eq!(
+            error("Match 1: 3 red"),
+            "line 1, column 1: expected \"Game\" or \"Round\", found \"Match 1\""
+        );
+        assert_eq!(
+            error("Game x: 3 red"),
+            "line 1, column 6: expected an unsigned integer, found \"x\""
+        );
+        assert_eq!(
+            error("Game 1 3 red"),
+            "line 1, column 1: expected \":\" in \"Game 1 3 red\""
+        );
+        assert_eq!(
+            error("Game 1: red"),
+            "line 1, column 9: expected a cube count, found \"red\""
+        );
+        assert_eq!(
+            error("Game 1: 3 red; 4"),
+            "line 1, column 16: missing cube color after 4"
+        );
+        assert_eq!(
+            error("Game 1: 3 red cubes"),
+            "line 1, column 15: unexpected \"cubes\" after the cube color"
+        );
+        assert_eq!(
+            error("Game 1: 3 red, 2 Red"),
+            "line 1, column 18: red is listed twice in one draw"
+        );
+        assert_eq!(
+            error("Game 1: 3 red\nGame 1: 2 blue"),
+            "line 2, column 1: game 1 is listed more than once"
+        );
+    }
+
+    #[test]
+    fn example_sum() {
+        let games = parse(EXAMPLE.to_string()).unwrap();
+        assert_eq!(process(games, &BagLimits::default()), 8);
+    }
 }
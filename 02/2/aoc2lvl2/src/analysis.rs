@@ -0,0 +1,290 @@
+//! what-if questions about which bags make which games possible
+//!
+//! a game is possible with a bag when the bag has at least the game's minimum count of every
+//! color, so everything here works off each game's minimum bag
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::Game;
+
+/// how many cubes of each color are in a bag
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bag {
+    /// u64 so an estimate can go past the biggest count a game records
+    pub counts: BTreeMap<String, u64>,
+}
+
+impl Bag {
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+}
+
+impl fmt::Display for Bag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = self
+            .counts
+            .iter()
+            .map(|(color, count)| format!("{} {}", count, color))
+            .collect::<Vec<_>>();
+        write!(f, "{} ({} cubes)", counts.join(", "), self.total())
+    }
+}
+
+/// the best anyone can do with a limited number of cubes
+#[derive(Debug)]
+pub struct BudgetReport {
+    pub budget: u32,
+    /// how many games there are in total
+    pub games: usize,
+    /// how many games the best bags make possible
+    pub possible: usize,
+    /// every smallest bag reaching that many games, with the ids of the games it allows
+    pub bags: Vec<(Bag, Vec<u32>)>,
+}
+
+impl fmt::Display for BudgetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "with {} cubes at most {} of {} games are possible",
+            self.budget, self.possible, self.games
+        )?;
+        for (bag, ids) in &self.bags {
+            if ids.is_empty() {
+                write!(f, "\n{}: no games", bag)?;
+            } else {
+                let ids = ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+                write!(f, "\n{}: games {}", bag, ids.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the smallest bag that makes every game possible
+pub fn minimal_bag(games: &[Game]) -> Bag {
+    let colors = colors(games);
+    let minimums = games
+        .iter()
+        .map(|game| minimum_counts(game, &colors))
+        .collect::<Vec<_>>();
+    bag_for(&colors, minimums.iter())
+}
+
+/// the bags of at most budget cubes that make the most games possible
+///
+/// only the smallest bag for each set of games is listed, any cubes left over from the
+/// budget can go anywhere without changing which games are possible
+pub fn best_bags(games: &[Game], budget: u32) -> BudgetReport {
+    let colors = colors(games);
+    let minimums = games
+        .iter()
+        .map(|game| minimum_counts(game, &colors))
+        .collect::<Vec<_>>();
+
+    // a best bag never needs more of a color than some game's minimum, so those
+    // (and none at all) are the only counts worth trying
+    let candidates = (0..colors.len())
+        .map(|c| {
+            let mut counts = minimums.iter().map(|m| m[c]).collect::<BTreeSet<_>>();
+            counts.insert(0);
+            counts.into_iter().collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut search = Search {
+        minimums: &minimums,
+        candidates: &candidates,
+        budget,
+        best: 0,
+        sets: BTreeSet::new(),
+    };
+    search.pick(0, 0, (0..games.len()).collect());
+
+    let mut bags = search
+        .sets
+        .into_iter()
+        .map(|set| {
+            let bag = bag_for(&colors, set.iter().map(|&g| &minimums[g]));
+            let ids = set.iter().map(|&g| games[g].id).collect();
+            (bag, ids)
+        })
+        .collect::<Vec<_>>();
+    bags.sort();
+
+    BudgetReport {
+        budget,
+        games: games.len(),
+        possible: search.best,
+        bags,
+    }
+}
+
+/// works through one color at a time, keeping track of which games are still possible
+struct Search<'a> {
+    minimums: &'a [Vec<u32>],
+    candidates: &'a [Vec<u32>],
+    budget: u32,
+    /// the most games any bag so far allows
+    best: usize,
+    /// every set of games reaching best
+    sets: BTreeSet<Vec<usize>>,
+}
+
+impl Search<'_> {
+    fn pick(&mut self, color: usize, spent: u32, possible: Vec<usize>) {
+        // picking more colors only ever rules games out
+        if possible.len() < self.best {
+            return;
+        }
+        if color == self.candidates.len() {
+            if possible.len() > self.best {
+                self.best = possible.len();
+                self.sets.clear();
+            }
+            self.sets.insert(possible);
+            return;
+        }
+
+        for &count in &self.candidates[color] {
+            // candidates go up, so once one is over budget (or past u32::MAX) they all are
+            let Some(spent) = spent.checked_add(count).filter(|&s| s <= self.budget) else {
+                break;
+            };
+            let still_possible = possible
+                .iter()
+                .copied()
+                .filter(|&g| self.minimums[g][color] <= count)
+                .collect();
+            self.pick(color + 1, spent, still_possible);
+        }
+    }
+}
+
+/// every color that shows up anywhere, sorted so bags list them consistently
+fn colors(games: &[Game]) -> Vec<String> {
+    games
+        .iter()
+        .flat_map(|game| game.draws())
+        .flat_map(|draw| draw.counts.keys())
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// the fewest cubes of each color (in the order given) a game could have been played with
+fn minimum_counts(game: &Game, colors: &[String]) -> Vec<u32> {
+    colors
+        .iter()
+        .map(|color| {
+            game.draws()
+                .filter_map(|draw| draw.counts.get(color))
+                .copied()
+                .max()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+/// the smallest bag covering every one of the minimums
+fn bag_for<'m>(colors: &[String], minimums: impl Iterator<Item = &'m Vec<u32>>) -> Bag {
+    let mut counts = vec![0; colors.len()];
+    for minimum in minimums {
+        for (count, &needed) in counts.iter_mut().zip(minimum) {
+            *count = needed.max(*count);
+        }
+    }
+    Bag {
+        counts: colors
+            .iter()
+            .cloned()
+            .zip(counts.into_iter().map(u64::from))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green
+";
+
+    fn games(input: &str) -> Vec<Game> {
+        crate::parse(input.to_string()).unwrap()
+    }
+
+    fn bag(counts: &[(&str, u64)]) -> Bag {
+        Bag {
+            counts: counts
+                .iter()
+                .map(|&(color, count)| (color.to_string(), count))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn example_minimal_bag() {
+        let minimal = minimal_bag(&games(EXAMPLE));
+        assert_eq!(minimal, bag(&[("blue", 15), ("green", 13), ("red", 20)]));
+        assert_eq!(minimal.to_string(), "15 blue, 13 green, 20 red (48 cubes)");
+    }
+
+    #[test]
+    fn example_budget() {
+        let report = best_bags(&games(EXAMPLE), 20);
+        assert_eq!(report.possible, 3);
+        assert_eq!(
+            report.bags,
+            [(bag(&[("blue", 6), ("green", 3), ("red", 6)]), vec![1, 2, 5])]
+        );
+    }
+
+    #[test]
+    fn ties_list_every_set_of_games() {
+        let report = best_bags(&games(EXAMPLE), 14);
+        assert_eq!(report.possible, 2);
+        assert_eq!(
+            report.bags,
+            [
+                (bag(&[("blue", 4), ("green", 3), ("red", 6)]), vec![2, 5]),
+                (bag(&[("blue", 6), ("green", 3), ("red", 4)]), vec![1, 2]),
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "with 14 cubes at most 2 of 5 games are possible\n\
+             4 blue, 3 green, 6 red (13 cubes): games 2, 5\n\
+             6 blue, 3 green, 4 red (13 cubes): games 1, 2"
+        );
+    }
+
+    #[test]
+    fn a_budget_of_nothing_allows_no_games() {
+        let report = best_bags(&games(EXAMPLE), 0);
+        assert_eq!(report.possible, 0);
+        assert_eq!(
+            report.bags,
+            [(bag(&[("blue", 0), ("green", 0), ("red", 0)]), vec![])]
+        );
+        assert!(report.to_string().ends_with("(0 cubes): no games"));
+    }
+
+    #[test]
+    fn huge_counts_dont_overflow_the_budget() {
+        let input = "Game 1: 4294967295 red, 4294967295 blue\nGame 2: 1 red";
+        let report = best_bags(&games(input), u32::MAX);
+        // game 1 needs twice the budget, which doesn't fit in a u32 either
+        assert_eq!(report.possible, 1);
+        assert_eq!(report.bags, [(bag(&[("blue", 0), ("red", 1)]), vec![2])]);
+        assert_eq!(minimal_bag(&games(input)).total(), 2 * u64::from(u32::MAX));
+    }
+}
@@ -0,0 +1,309 @@
+//! guess what's in the bag from the draws a game recorded
+//!
+//! each draw pulls a handful of cubes out without replacement and puts them all back
+//! afterwards, so the chance of one draw is multivariate hypergeometric and the draws are
+//! independent. we try every composition from the game's minimum bag up to `spread` extra
+//! cubes of each color and keep the most likely one.
+//!
+//! the search covers (spread + 1) ^ colors bags, which gets out of hand quickly with a lot of
+//! colors, so a search bigger than `MAX_BAGS` is refused rather than left to run for minutes.
+//!
+//! the range for a color is where its profile likelihood (the best likelihood with that color
+//! fixed) stays within 1.92 of the maximum, the usual likelihood ratio cutoff for about 95%
+//! confidence. a range reaching the top of the search is marked open since more cubes might
+//! fit just as well.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::analysis::Bag;
+use crate::Game;
+
+/// the most bags one game's search will try
+pub const MAX_BAGS: u64 = 1_000_000;
+
+// half the 95% quantile of a chi-squared distribution with one degree of freedom
+const CONFIDENCE_DROP: f64 = 1.92;
+
+/// the most likely bag for a game and how sure we can be about each color
+#[derive(Debug)]
+pub struct Estimate {
+    pub id: u32,
+    pub bag: Bag,
+    pub log_likelihood: f64,
+    /// some color of the best bag sits at the top of the search, so a bigger bag may do better
+    pub capped: bool,
+    pub ranges: BTreeMap<String, CountRange>,
+}
+
+/// the plausible counts of one color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountRange {
+    pub low: u64,
+    pub high: u64,
+    /// high is only where the search stopped, the range could go on past it
+    pub open: bool,
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bag.counts.is_empty() {
+            return write!(f, "game {}: no cubes drawn, nothing to estimate", self.id);
+        }
+        write!(
+            f,
+            "game {}: {}, ln likelihood {:.3}",
+            self.id, self.bag, self.log_likelihood
+        )?;
+        if self.capped {
+            write!(f, " (search limit reached, try a bigger --spread)")?;
+        }
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|(color, r)| {
+                let open = if r.open { "+" } else { "" };
+                format!("{} {}-{}{}", color, r.low, r.high, open)
+            })
+            .collect::<Vec<_>>();
+        if !ranges.is_empty() {
+            write!(f, ", 95% ranges {}", ranges.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// the maximum likelihood bag for a game, searching up to spread extra cubes of each color
+///
+/// fails if that's more than `MAX_BAGS` bags to try
+pub fn estimate(game: &Game, spread: u32) -> anyhow::Result<Estimate> {
+    let colors = game
+        .draws()
+        .flat_map(|draw| draw.counts.keys())
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let bags = u32::try_from(colors.len())
+        .ok()
+        .and_then(|colors| (u64::from(spread) + 1).checked_pow(colors))
+        .filter(|&bags| bags <= MAX_BAGS);
+    if bags.is_none() {
+        anyhow::bail!(
+            "game {}: {} extra cubes of each of {} colors is more than {} bags to search, \
+             try a smaller --spread",
+            game.id,
+            spread,
+            colors.len(),
+            MAX_BAGS
+        );
+    }
+
+    // u64 counts so a count plus the spread, or every count in a bag added up, can't overflow
+    let draws = game
+        .draws()
+        .map(|draw| {
+            colors
+                .iter()
+                .map(|color| draw.counts.get(color).copied().map_or(0, u64::from))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let minimum = (0..colors.len())
+        .map(|c| draws.iter().map(|draw| draw[c]).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+    let spread = u64::from(spread);
+
+    let mut bag = minimum.clone();
+    let mut best = (f64::NEG_INFINITY, minimum.clone());
+    // best likelihood seen with each color fixed at each count
+    let mut profiles = vec![vec![f64::NEG_INFINITY; spread as usize + 1]; colors.len()];
+    'search: loop {
+        let likelihood = log_likelihood(&bag, &draws);
+        if likelihood > best.0 {
+            best = (likelihood, bag.clone());
+        }
+        for (c, profile) in profiles.iter_mut().enumerate() {
+            let p = &mut profile[(bag[c] - minimum[c]) as usize];
+            *p = p.max(likelihood);
+        }
+
+        // step to the next composition like an odometer
+        let mut c = 0;
+        loop {
+            if c == colors.len() {
+                break 'search;
+            }
+            if bag[c] < minimum[c] + spread {
+                bag[c] += 1;
+                break;
+            }
+            bag[c] = minimum[c];
+            c += 1;
+        }
+    }
+
+    let (log_likelihood, counts) = best;
+    let capped = counts
+        .iter()
+        .zip(&minimum)
+        .any(|(&count, &min)| count == min + spread);
+    let ranges = colors
+        .iter()
+        .zip(&profiles)
+        .zip(&minimum)
+        .map(|((color, profile), &min)| {
+            let plausible = profile
+                .iter()
+                .enumerate()
+                .filter(|(_, &p)| p >= log_likelihood - CONFIDENCE_DROP)
+                .map(|(i, _)| min + i as u64);
+            let low = plausible.clone().min().unwrap_or(min);
+            let high = plausible.max().unwrap_or(min);
+            let range = CountRange {
+                low,
+                high,
+                open: high == min + spread,
+            };
+            (color.clone(), range)
+        })
+        .collect();
+
+    Ok(Estimate {
+        id: game.id,
+        bag: Bag {
+            counts: colors.into_iter().zip(counts).collect(),
+        },
+        log_likelihood,
+        capped,
+        ranges,
+    })
+}
+
+/// ln of the chance of every draw happening with the given bag
+fn log_likelihood(bag: &[u64], draws: &[Vec<u64>]) -> f64 {
+    let ln_choose = |n: u64, k: u64| ln_factorial(n) - ln_factorial(k) - ln_factorial(n - k);
+    let total = bag.iter().sum::<u64>();
+    draws
+        .iter()
+        .map(|draw| {
+            let ways = bag
+                .iter()
+                .zip(draw)
+                .map(|(&n, &k)| ln_choose(n, k))
+                .sum::<f64>();
+            ways - ln_choose(total, draw.iter().sum())
+        })
+        .sum()
+}
+
+/// ln(n!), exactly for small n and by stirling's series past that
+///
+/// a table of every factorial up to the biggest bag would need gigabytes for a game with
+/// billions of cubes
+fn ln_factorial(n: u64) -> f64 {
+    if n < 16 {
+        return (2..=n).map(|k| (k as f64).ln()).sum();
+    }
+    // the terms after these are below 1e-17 from n = 16 on
+    let n = n as f64;
+    n * n.ln() - n + 0.5 * (std::f64::consts::TAU * n).ln() + 1.0 / (12.0 * n)
+        - 1.0 / (360.0 * n.powi(3))
+        + 1.0 / (1260.0 * n.powi(5))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estimate_for(record: &str, spread: u32) -> anyhow::Result<Estimate> {
+        let games = crate::parse(record.to_string()).unwrap();
+        estimate(&games[0], spread)
+    }
+
+    #[test]
+    fn seeing_each_color_alone_suggests_more_of_both() {
+        // with r red and b blue, the chance of drawing 3 red then 3 blue is
+        // C(r,3)/C(r+b,3) * C(b,3)/C(r+b,3): 1/400 for the minimum of 3 and 3, rising to
+        // (10/120)^2 = 1/144 for 5 and 5, the biggest bag a spread of 2 allows
+        let estimate = estimate_for("Game 1: 3 red; 3 blue", 2).unwrap();
+        assert_eq!(estimate.bag.counts["red"], 5);
+        assert_eq!(estimate.bag.counts["blue"], 5);
+        assert!((estimate.log_likelihood + 144f64.ln()).abs() < 1e-9);
+        // it keeps rising past the edge of the search
+        assert!(estimate.capped);
+        // red at 3 is at best 4/35^2 (with 4 blue), within the cutoff of 1/144
+        let red = CountRange {
+            low: 3,
+            high: 5,
+            open: true,
+        };
+        assert_eq!(estimate.ranges["red"], red);
+        assert_eq!(estimate.ranges["blue"], red);
+        assert!(estimate.to_string().ends_with(
+            "(search limit reached, try a bigger --spread), 95% ranges blue 3-5+, red 3-5+"
+        ));
+    }
+
+    #[test]
+    fn a_draw_of_the_whole_bag_is_certain() {
+        // 3 red and 3 blue in one draw can only come from a bag of exactly that
+        let estimate = estimate_for("Game 2: 3 red, 3 blue", 4).unwrap();
+        assert_eq!(estimate.bag.total(), 6);
+        assert_eq!(estimate.log_likelihood, 0.0);
+        assert!(!estimate.capped);
+        // but one draw can't rule much out, a bag with plenty more of each color is only
+        // about e^-1 as likely
+        let range = estimate.ranges["red"];
+        assert_eq!((range.low, range.high, range.open), (3, 7, true));
+    }
+
+    #[test]
+    fn games_without_draws_have_nothing_to_estimate() {
+        let estimate = estimate_for("Game 3:", 20).unwrap();
+        assert!(estimate.bag.counts.is_empty());
+        assert!(estimate.ranges.is_empty());
+        assert!(!estimate.capped);
+        assert_eq!(
+            estimate.to_string(),
+            "game 3: no cubes drawn, nothing to estimate"
+        );
+    }
+
+    #[test]
+    fn huge_counts_dont_need_huge_tables() {
+        let estimate = estimate_for("Game 1: 4000000000 red", 1).unwrap();
+        assert_eq!(estimate.bag.counts["red"], 4_000_000_000);
+        let estimate = estimate_for("Game 1: 4294967295 red, 4294967295 blue", 1).unwrap();
+        assert_eq!(estimate.bag.total(), 2 * u64::from(u32::MAX));
+        let range = estimate.ranges["red"];
+        assert_eq!(range.high, u64::from(u32::MAX) + 1);
+    }
+
+    #[test]
+    fn searches_too_big_to_finish_are_refused() {
+        let six_colors = "Game 4: 1 a, 1 b, 1 c, 1 d, 1 e, 1 f";
+        let err = estimate_for(six_colors, 20).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "game 4: 20 extra cubes of each of 6 colors is more than 1000000 bags to search, \
+             try a smaller --spread"
+        );
+        assert!(estimate_for(six_colors, 9).is_ok());
+        assert!(estimate_for("Game 1: 1 a, 1 b", u32::MAX).is_err());
+    }
+
+    #[test]
+    fn stirling_matches_the_exact_factorials() {
+        let mut exact = 0.0;
+        for n in 1..200u64 {
+            exact += (n as f64).ln();
+            assert!(
+                (ln_factorial(n) - exact).abs() <= 1e-12 * exact.max(1.0),
+                "{}",
+                n
+            );
+        }
+        assert_eq!(ln_factorial(0), 0.0);
+    }
+}
@@ -1,37 +1,98 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
 extern crate clap;
 use clap::Parser;
 
 extern crate anyhow;
 
+extern crate aoccommon;
+use aoccommon::alloc;
+use aoccommon::batch;
+use aoccommon::config::AocConfig;
+use aoccommon::parsing::{self, Span};
+
+mod analysis;
+mod estimate;
+
 #[derive(Parser, Debug)]
 pub struct Args {
     /// path to the input file
     #[arg(short, long)]
     input: Option<PathBuf>,
+    /// path to a directory of input files to solve in parallel
+    #[arg(long, conflicts_with = "input")]
+    inputs: Option<PathBuf>,
+    /// print the smallest bag that makes every game possible instead of the total power
+    #[arg(long)]
+    minimal_bag: bool,
+    /// print the bags of at most this many cubes that make the most games possible
+    #[arg(long, value_name = "CUBES", conflicts_with = "minimal_bag")]
+    budget: Option<u32>,
+    /// print the most likely bag for each game along with a 95% range per color
+    #[arg(long, conflicts_with_all = ["minimal_bag", "budget"])]
+    estimate: bool,
+    /// how many cubes past each game's minimum the estimate searches, per color
+    #[arg(
+        long,
+        value_name = "CUBES",
+        default_value_t = 20,
+        requires = "estimate"
+    )]
+    spread: u32,
 }
 
 enum InputConfig {
     File(PathBuf),
     Stdin,
+    Batch(PathBuf, Option<Duration>),
 }
+/// what to work out from the games
+enum Analysis {
+    Power,
+    MinimalBag,
+    Budget(u32),
+    /// with the spread to search
+    Estimate(u32),
+}
+
 pub struct Config {
     input: InputConfig,
+    analysis: Analysis,
 }
 
 impl Config {
-    pub fn make() -> Self {
+    pub fn make() -> anyhow::Result<Self> {
         let args = Args::parse();
-        let input = if let Some(path) = args.input {
+        let file_cfg = AocConfig::load()?;
+        // command line input wins over the config file
+        let input = if let Some(dir) = args.inputs {
+            InputConfig::Batch(dir, file_cfg.timeout())
+        } else if let Some(path) = args
+            .input
+            .map(Ok)
+            .or_else(|| file_cfg.input_path(2).transpose())
+            .transpose()?
+        {
             InputConfig::File(path)
         } else {
             InputConfig::Stdin
         };
 
-        Config { input }
+        let analysis = if args.minimal_bag {
+            Analysis::MinimalBag
+        } else if let Some(budget) = args.budget {
+            Analysis::Budget(budget)
+        } else if args.estimate {
+            Analysis::Estimate(args.spread)
+        } else {
+            Analysis::Power
+        };
+
+        Ok(Config { input, analysis })
     }
 }
 
@@ -44,78 +105,176 @@ pub fn run(cfg: Config) -> anyhow::Result<()> {
             io::stdin().read_line(&mut buf)?;
             buf
         }
+        InputConfig::Batch(dir, timeout) => {
+            let report = batch::run_batch(&dir, timeout, move |input| solve(input, &cfg.analysis))?;
+            println!("{}", report);
+            if report.failures() > 0 {
+                anyhow::bail!(
+                    "{} of {} inputs failed",
+                    report.failures(),
+                    report.results.len()
+                );
+            }
+            return Ok(());
+        }
     };
 
-    let data = parse(input_string)?;
-    let result = process(data);
+    let result = solve(input_string, &cfg.analysis)?;
 
     println!("{}", result);
 
     Ok(())
 }
 
-/// parse game strings into games containing samples of optional color counts
+/// parse and process a single input
+fn solve(input: String, analysis: &Analysis) -> anyhow::Result<String> {
+    let data = alloc::measure("parse", || parse(input))?;
+    alloc::measure("solve", || match analysis {
+        Analysis::Power => Ok(process(data)?.to_string()),
+        Analysis::MinimalBag => Ok(analysis::minimal_bag(&data).to_string()),
+        Analysis::Budget(budget) => Ok(analysis::best_bags(&data, *budget).to_string()),
+        Analysis::Estimate(spread) => Ok(data
+            .iter()
+            .map(|game| Ok(estimate::estimate(game, *spread)?.to_string()))
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .join("\n")),
+    })
+}
+
+/// labels a game record can start with, matched ignoring case
+const LABELS: [&str; 2] = ["game", "round"];
+
+/// parse game strings into games containing samples of color counts
+///
+/// the grammar is forgiving: labels and colors can be in any case, whitespace can vary,
+/// blank lines and empty draws are skipped, and a count can run straight into its color
 fn parse(input: String) -> anyhow::Result<Vec<Game>> {
     let mut games = Vec::new();
+    let mut seen = HashSet::new();
+
+    for line in parsing::lines(&input) {
+        if line.trim().is_empty() {
+            continue;
+        }
 
-    for line in input.lines() {
-        let game_label_end = line.find(':').expect("invalid input, missing colon");
-        let id = line[5..game_label_end].parse::<u32>()?;
+        let game = parse_game(line)?;
+        if !seen.insert(game.id) {
+            return Err(line
+                .trim()
+                .error(format!("game {} is listed more than once", game.id))
+                .into());
+        }
+        games.push(game);
+    }
 
-        let mut cur_game = Game {
-            id,
-            samples: Vec::new(),
-        };
+    Ok(games)
+}
+
+/// `Game 12: 3 blue, 4 red; 2 green`
+fn parse_game(line: Span<'_>) -> parsing::Result<Game> {
+    let (head, draws) = line.split_once(":")?;
 
-        for sample_str in line[game_label_end + 2..].split("; ") {
-            let (mut red, mut green, mut blue) = (None, None, None);
-            for cube_sample in sample_str.split(", ") {
-                let mut iter = cube_sample.split(' ');
-                let count = iter
-                    .next()
-                    .expect("missing cube count in sample")
-                    .parse::<u32>()
-                    .expect("bad cube count");
-                let color = iter.next().expect("missing cube color in sample");
-
-                match color {
-                    "red" => red = Some(count),
-                    "green" => green = Some(count),
-                    "blue" => blue = Some(count),
-                    _ => panic!("bad cube color in sample"),
-                };
-
-                cur_game.samples.push(Sample { red, green, blue });
+    let head = head.trim();
+    let label_len = head
+        .as_str()
+        .find(|c: char| !c.is_alphabetic())
+        .unwrap_or(head.as_str().len());
+    let label = head.slice(0, label_len);
+    if !LABELS
+        .iter()
+        .any(|l| label.as_str().eq_ignore_ascii_case(l))
+    {
+        return Err(label.error(format!(
+            "expected \"Game\" or \"Round\", found {:?}",
+            head.as_str()
+        )));
+    }
+    let id = head
+        .slice(label_len, head.as_str().len())
+        .parse::<u32>("an unsigned integer")?;
+
+    let mut game = Game {
+        id,
+        samples: Vec::new(),
+    };
+    for draw in draws.list(";").filter(|draw| !draw.is_empty()) {
+        let mut counts = HashMap::new();
+        for cube in draw.list(",").filter(|cube| !cube.is_empty()) {
+            let (count, color) = parse_cubes(cube)?;
+            let name = color.as_str().to_lowercase();
+            if counts.insert(name.clone(), count).is_some() {
+                return Err(color.error(format!("{} is listed twice in one draw", name)));
             }
         }
+        game.samples.push(Sample { counts });
+    }
+
+    Ok(game)
+}
 
-        games.push(cur_game);
+/// `3 blue` (or `3blue`), giving back the count and the lowercased color
+fn parse_cubes(cube: Span<'_>) -> parsing::Result<(u32, Span<'_>)> {
+    let text = cube.as_str();
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    if digits == 0 {
+        return Err(cube.error(format!("expected a cube count, found {:?}", text)));
     }
+    let count = cube.slice(0, digits).parse::<u32>("an unsigned integer")?;
 
-    Ok(games)
+    let mut words = cube.slice(digits, text.len()).words();
+    let color = words
+        .next()
+        .ok_or_else(|| cube.error(format!("missing cube color after {}", count)))?;
+    if let Some(extra) = words.next() {
+        return Err(extra.error(format!(
+            "unexpected {:?} after the cube color",
+            extra.as_str()
+        )));
+    }
+
+    Ok((count, color))
 }
 
 /// process game structs to get our answer
-fn process(games: Vec<Game>) -> u32 {
-    let mut total_power = 0;
+///
+/// a game's power multiplies its minimum count for every color seen anywhere in the input,
+/// so a game that never shows one of them has a power of 0. with no cubes in the input at
+/// all there's nothing to multiply, and every game's power is 0 rather than the empty
+/// product's 1. the arithmetic is checked, a total too big for a u128 is an error rather
+/// than quietly wrapping around
+fn process(games: Vec<Game>) -> anyhow::Result<u128> {
+    let colors = games
+        .iter()
+        .flat_map(|game| game.draws())
+        .flat_map(|sample| sample.counts.keys())
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    if colors.is_empty() {
+        return Ok(0);
+    }
+
+    let too_big = || anyhow::anyhow!("the total power is too big to fit in 128 bits");
+    let mut total_power = 0u128;
     for game in games {
-        let (mut min_red, mut min_green, mut min_blue) = (0, 0, 0);
-        for sample in game.samples {
-            if let Some(red) = sample.red {
-                min_red = min_red.max(red);
-            }
-            if let Some(green) = sample.green {
-                min_green = min_green.max(green);
-            }
-            if let Some(blue) = sample.blue {
-                min_blue = min_blue.max(blue);
+        let mut min_counts = HashMap::new();
+        for sample in game.draws() {
+            for (color, &count) in &sample.counts {
+                let min = min_counts.entry(color).or_insert(0);
+                *min = count.max(*min);
             }
         }
 
-        total_power += min_red * min_green * min_blue;
+        let power = colors
+            .iter()
+            .map(|color| min_counts.get(color).copied().unwrap_or(0))
+            .try_fold(1u128, |power, count| power.checked_mul(count.into()))
+            .ok_or_else(too_big)?;
+        total_power = total_power.checked_add(power).ok_or_else(too_big)?;
     }
 
-    total_power
+    Ok(total_power)
 }
 
 #[derive(Debug)]
@@ -124,9 +283,88 @@ struct Game {
     samples: Vec<Sample>,
 }
 
+impl Game {
+    /// every handful of cubes shown during the game, in order
+    fn draws(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+}
+
+/// one handful of cubes, everything between a pair of semicolons
 #[derive(Debug)]
 struct Sample {
-    red: Option<u32>,
-    green: Option<u32>,
-    blue: Option<u32>,
+    /// how many cubes of each color were shown, colors that weren't shown are left out
+    counts: HashMap<String, u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green
+";
+
+    #[test]
+    fn one_sample_per_draw() {
+        let games = parse(EXAMPLE.to_string()).unwrap();
+        let draws = games.iter().map(|g| g.draws().count()).collect::<Vec<_>>();
+        assert_eq!(draws, vec![3, 3, 3, 3, 2]);
+
+        let first = games[0].draws().next().unwrap();
+        assert_eq!(first.counts.len(), 2);
+        assert_eq!(first.counts["blue"], 3);
+        assert_eq!(first.counts["red"], 4);
+    }
+
+    #[test]
+    fn example_power() {
+        assert_eq!(process(parse(EXAMPLE.to_string()).unwrap()).unwrap(), 2286);
+    }
+
+    fn power(input: &str) -> anyhow::Result<u128> {
+        process(parse(input.to_string()).unwrap())
+    }
+
+    #[test]
+    fn powers_past_u32_are_exact() {
+        assert_eq!(
+            power("Game 1: 100 a, 100 b, 100 c, 100 d, 100 e").unwrap(),
+            10_000_000_000
+        );
+        assert_eq!(
+            power("Game 1: 3000000000 red\nGame 2: 3000000000 red").unwrap(),
+            6_000_000_000
+        );
+        let max = u128::from(u32::MAX);
+        assert_eq!(
+            power("Game 1: 4294967295 a, 4294967295 b, 4294967295 c").unwrap(),
+            max * max * max
+        );
+    }
+
+    #[test]
+    fn games_without_cubes_have_no_power() {
+        assert_eq!(power("Game 1:\nGame 2:").unwrap(), 0);
+        // an empty game next to others still counts for nothing
+        assert_eq!(power("Game 1:\nGame 2: 3 red; 2 red").unwrap(), 3);
+    }
+
+    #[test]
+    fn powers_past_u128_are_errors() {
+        let colors = "abcde"
+            .chars()
+            .map(|c| format!("4294967295 {}", c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let err = power(&format!("Game 1: {}", colors)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the total power is too big to fit in 128 bits"
+        );
+    }
 }
@@ -3,7 +3,7 @@ use aoc2lvl2::Config;
 use std::process;
 fn main() {
     // remember to change the module name!
-    if let Err(e) = aoc2lvl2::run(Config::make()) {
+    if let Err(e) = Config::make().and_then(aoc2lvl2::run) {
         eprintln!("{}", e);
         process::exit(1);
     } else {
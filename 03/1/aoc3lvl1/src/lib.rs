@@ -1,41 +1,118 @@
-use std::collections::HashSet;
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
 extern crate clap;
 use clap::Parser;
 
 extern crate anyhow;
 
-extern crate regex;
-use regex::Regex;
+extern crate aoccommon;
+use aoccommon::alloc;
+use aoccommon::batch;
+use aoccommon::config::AocConfig;
+use aoccommon::schematic::{
+    Adjacency, Aggregate, GraphFormat, Neighbors, Query, Schematic, Scope, Style, SymbolSet,
+};
 
 #[derive(Parser, Debug)]
 pub struct Args {
     /// path to the input file
     #[arg(short, long)]
     input: Option<PathBuf>,
+    /// path to a directory of input files to solve in parallel
+    #[arg(long, conflicts_with = "input")]
+    inputs: Option<PathBuf>,
+    /// characters that count as symbols (default: anything but digits, dots and whitespace)
+    #[arg(long, value_name = "CHARS")]
+    symbols: Option<SymbolSet>,
+    /// when a number is next to a symbol: around (the eight cells), orthogonal, diagonal,
+    /// chebyshev:N or manhattan:N
+    #[arg(long, value_name = "MODE")]
+    adjacency: Option<Adjacency>,
+    /// only look around these symbols
+    #[arg(long, value_name = "CHARS")]
+    kinds: Option<String>,
+    /// how many numbers a symbol needs around it: N, >=N or <=N
+    #[arg(long, value_name = "RULE")]
+    neighbors: Option<Neighbors>,
+    /// combine the numbers around each symbol separately (each) or all together (all)
+    #[arg(long)]
+    scope: Option<Scope>,
+    /// how to combine numbers: sum, product or count
+    #[arg(long)]
+    aggregate: Option<Aggregate>,
+    /// also draw the schematic with part numbers, loose numbers and the symbols the query matches
+    /// marked, in color or as plain markers (defaults to color when printing to a terminal)
+    #[arg(long, value_name = "STYLE", num_args = 0..=1, conflicts_with = "inputs")]
+    render: Option<Option<Style>>,
+    /// print the graph of which numbers touch which symbols as dot or json instead of the
+    /// answer, which goes to stderr
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        conflicts_with_all = ["inputs", "render"]
+    )]
+    graph: Option<Option<GraphFormat>>,
 }
 
 enum InputConfig {
     File(PathBuf),
     Stdin,
+    Batch(PathBuf, Option<Duration>),
 }
 pub struct Config {
     input: InputConfig,
+    symbols: SymbolSet,
+    adjacency: Adjacency,
+    query: Query,
+    render: Option<Style>,
+    graph: Option<GraphFormat>,
 }
 
 impl Config {
-    pub fn make() -> Self {
+    pub fn make() -> anyhow::Result<Self> {
         let args = Args::parse();
-        let input = if let Some(path) = args.input {
+        let file_cfg = AocConfig::load()?;
+        // command line input wins over the config file
+        let input = if let Some(dir) = args.inputs {
+            InputConfig::Batch(dir, file_cfg.timeout())
+        } else if let Some(path) = args
+            .input
+            .map(Ok)
+            .or_else(|| file_cfg.input_path(3).transpose())
+            .transpose()?
+        {
             InputConfig::File(path)
         } else {
             InputConfig::Stdin
         };
 
-        Config { input }
+        // by default we answer the puzzle, any query options change just that part of it
+        let mut query = Query::part_numbers();
+        if let Some(kinds) = args.kinds {
+            query.kinds = Some(kinds.chars().collect());
+        }
+        if let Some(neighbors) = args.neighbors {
+            query.neighbors = neighbors;
+        }
+        if let Some(scope) = args.scope {
+            query.scope = scope;
+        }
+        if let Some(aggregate) = args.aggregate {
+            query.aggregate = aggregate;
+        }
+
+        Ok(Config {
+            input,
+            symbols: args.symbols.unwrap_or_default(),
+            adjacency: args.adjacency.unwrap_or_default(),
+            query,
+            render: args.render.map(|style| style.unwrap_or_else(Style::detect)),
+            graph: args.graph.map(Option::unwrap_or_default),
+        })
     }
 }
 
@@ -48,67 +125,154 @@ pub fn run(cfg: Config) -> anyhow::Result<()> {
             io::stdin().read_line(&mut buf)?;
             buf
         }
+        InputConfig::Batch(dir, timeout) => {
+            let report = batch::run_batch(&dir, timeout, move |input| {
+                solve(input, &cfg.symbols, cfg.adjacency, &cfg.query)
+            })?;
+            println!("{}", report);
+            if report.failures() > 0 {
+                anyhow::bail!(
+                    "{} of {} inputs failed",
+                    report.failures(),
+                    report.results.len()
+                );
+            }
+            return Ok(());
+        }
     };
 
-    let result = process(input_string)?;
+    if let Some(style) = cfg.render {
+        print!(
+            "{}",
+            Schematic::parse(&input_string, &cfg.symbols)?
+                .with_adjacency(cfg.adjacency)
+                .render(style, &cfg.query)
+        );
+    }
+    // the graph has stdout to itself so it can be piped straight into other tools
+    if let Some(format) = cfg.graph {
+        let graph = Schematic::parse(&input_string, &cfg.symbols)?
+            .with_adjacency(cfg.adjacency)
+            .graph();
+        println!("{}", graph.write(format)?.trim_end());
+        eprintln!(
+            "total: {}",
+            solve(input_string, &cfg.symbols, cfg.adjacency, &cfg.query)?
+        );
+        return Ok(());
+    }
+
+    let result = solve(input_string, &cfg.symbols, cfg.adjacency, &cfg.query)?;
 
     println!("{}", result);
 
     Ok(())
 }
 
-// seperate input into lines, find numbers, and find symbols
-fn process(input: String) -> anyhow::Result<u32> {
-    // offsets for finding neighbors of symbols
-    const OFFSETS: [(i32, i32); 8] = [
-        (0, 1),
-        (0, -1),
-        (1, 1),
-        (1, -1),
-        (1, 0),
-        (-1, 1),
-        (-1, -1),
-        (-1, 0),
-    ];
-    // regex for finding numbers
-    let num_pattern = Regex::new(r"\d+").unwrap();
-    // regex for finding symbols
-    let symbol_pattern = Regex::new(r"[^\d\.\s]").unwrap();
-
-    // Hash set to keep track of which positions neighbor symbols
-    let mut symbol_neighbors = HashSet::new();
-
-    let linecount = input.lines().count() as i32;
-
-    // find symbols and record their neighbors
-    for (lineno, line) in input.lines().enumerate() {
-        for symbol_match in symbol_pattern.find_iter(line) {
-            for offset in OFFSETS {
-                let neighbor_line = lineno as i32 + offset.0;
-                let neighbor_col = symbol_match.start() as i32 + offset.1;
-                if 0 <= neighbor_line
-                    && neighbor_line < linecount
-                    && 0 <= neighbor_col
-                    && neighbor_col < line.len() as i32
-                {
-                    symbol_neighbors.insert((neighbor_line as usize, neighbor_col as usize));
-                }
-            }
+/// parse and process a single input
+fn solve(
+    input: String,
+    symbols: &SymbolSet,
+    adjacency: Adjacency,
+    query: &Query,
+) -> anyhow::Result<u128> {
+    process(input, symbols, adjacency, query)
+}
+
+// index the numbers and symbols, then answer the query (the sum of every number next to a symbol unless told otherwise)
+fn process(
+    input: String,
+    symbols: &SymbolSet,
+    adjacency: Adjacency,
+    query: &Query,
+) -> anyhow::Result<u128> {
+    let schematic =
+        alloc::measure("parse", || Schematic::parse(&input, symbols))?.with_adjacency(adjacency);
+    alloc::measure("solve", || schematic.query(query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoccommon::schematic::LiveSchematic;
+
+    const EXAMPLE: &str = "\
+467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..
+";
+
+    fn answer(adjacency: &str) -> u128 {
+        let query = Query::part_numbers();
+        let adjacency = adjacency.parse().unwrap();
+        process(EXAMPLE.to_string(), &SymbolSet::Default, adjacency, &query).unwrap()
+    }
+
+    #[test]
+    fn example_part_numbers_by_adjacency() {
+        // 467, 592 and 755 only touch a symbol through a corner and 617 only along an edge,
+        // while a reach of two picks up 58, and 114 as well when it's counted in rows and columns
+        for (adjacency, expected) in [
+            ("around", 4361),
+            ("orthogonal", 2547),
+            ("diagonal", 3744),
+            ("chebyshev:2", 4533),
+            ("manhattan:2", 4419),
+            ("chebyshev:0", 0),
+        ] {
+            assert_eq!(answer(adjacency), expected, "{}", adjacency);
         }
     }
 
-    let mut part_no_sum = 0;
-    // for each number, check if it's by a symbol, and if so add to sum
-    for (lineno, line) in input.lines().enumerate() {
-        'match_loop: for num_match in num_pattern.find_iter(line) {
-            for colno in num_match.range() {
-                if symbol_neighbors.contains(&(lineno, colno)) {
-                    part_no_sum += num_match.as_str().parse::<u32>().unwrap();
-                    continue 'match_loop;
-                }
+    #[test]
+    fn live_edits_match_a_full_recompute() {
+        // a small linear congruential generator keeps the edits random but repeatable
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move |n: usize| {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 33) as usize % n
+        };
+        let cells = [
+            '.', '.', '.', '.', '.', '1', '2', '5', '9', '0', '*', '*', '#', '日', '\t',
+        ];
+
+        for adjacency in [
+            "around",
+            "orthogonal",
+            "diagonal",
+            "manhattan:2",
+            "chebyshev:2",
+        ] {
+            let adjacency = adjacency.parse().unwrap();
+            let mut live = LiveSchematic::new(EXAMPLE, SymbolSet::Default, adjacency).unwrap();
+            for _ in 0..400 {
+                // a little past the example's edges so rows and columns grow too
+                let pos = (next(13), next(13));
+                live.set(pos, cells[next(cells.len())]).unwrap();
+                let full = process(
+                    live.to_string(),
+                    &SymbolSet::Default,
+                    adjacency,
+                    &Query::part_numbers(),
+                )
+                .unwrap();
+                assert_eq!(
+                    live.part_sum(),
+                    full,
+                    "{:?} after editing\n{}",
+                    adjacency,
+                    live
+                );
             }
         }
     }
-
-    Ok(part_no_sum)
 }
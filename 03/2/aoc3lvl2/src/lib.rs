@@ -1,41 +1,118 @@
-use std::collections::HashSet;
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
 extern crate clap;
 use clap::Parser;
 
 extern crate anyhow;
 
-extern crate regex;
-use regex::Regex;
+extern crate aoccommon;
+use aoccommon::alloc;
+use aoccommon::batch;
+use aoccommon::config::AocConfig;
+use aoccommon::schematic::{
+    Adjacency, Aggregate, GraphFormat, Neighbors, Query, Schematic, Scope, Style, SymbolSet,
+};
 
 #[derive(Parser, Debug)]
 pub struct Args {
     /// path to the input file
     #[arg(short, long)]
     input: Option<PathBuf>,
+    /// path to a directory of input files to solve in parallel
+    #[arg(long, conflicts_with = "input")]
+    inputs: Option<PathBuf>,
+    /// characters that count as symbols (default: anything but digits, dots and whitespace)
+    #[arg(long, value_name = "CHARS")]
+    symbols: Option<SymbolSet>,
+    /// when a number is next to a symbol: around (the eight cells), orthogonal, diagonal,
+    /// chebyshev:N or manhattan:N
+    #[arg(long, value_name = "MODE")]
+    adjacency: Option<Adjacency>,
+    /// only look around these symbols
+    #[arg(long, value_name = "CHARS")]
+    kinds: Option<String>,
+    /// how many numbers a symbol needs around it: N, >=N or <=N
+    #[arg(long, value_name = "RULE")]
+    neighbors: Option<Neighbors>,
+    /// combine the numbers around each symbol separately (each) or all together (all)
+    #[arg(long)]
+    scope: Option<Scope>,
+    /// how to combine numbers: sum, product or count
+    #[arg(long)]
+    aggregate: Option<Aggregate>,
+    /// also draw the schematic with part numbers, loose numbers and the symbols the query matches
+    /// marked, in color or as plain markers (defaults to color when printing to a terminal)
+    #[arg(long, value_name = "STYLE", num_args = 0..=1, conflicts_with = "inputs")]
+    render: Option<Option<Style>>,
+    /// print the graph of which numbers touch which symbols as dot or json instead of the
+    /// answer, which goes to stderr
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        conflicts_with_all = ["inputs", "render"]
+    )]
+    graph: Option<Option<GraphFormat>>,
 }
 
 enum InputConfig {
     File(PathBuf),
     Stdin,
+    Batch(PathBuf, Option<Duration>),
 }
 pub struct Config {
     input: InputConfig,
+    symbols: SymbolSet,
+    adjacency: Adjacency,
+    query: Query,
+    render: Option<Style>,
+    graph: Option<GraphFormat>,
 }
 
 impl Config {
-    pub fn make() -> Self {
+    pub fn make() -> anyhow::Result<Self> {
         let args = Args::parse();
-        let input = if let Some(path) = args.input {
+        let file_cfg = AocConfig::load()?;
+        // command line input wins over the config file
+        let input = if let Some(dir) = args.inputs {
+            InputConfig::Batch(dir, file_cfg.timeout())
+        } else if let Some(path) = args
+            .input
+            .map(Ok)
+            .or_else(|| file_cfg.input_path(3).transpose())
+            .transpose()?
+        {
             InputConfig::File(path)
         } else {
             InputConfig::Stdin
         };
 
-        Config { input }
+        // by default we answer the puzzle, any query options change just that part of it
+        let mut query = Query::gear_ratios();
+        if let Some(kinds) = args.kinds {
+            query.kinds = Some(kinds.chars().collect());
+        }
+        if let Some(neighbors) = args.neighbors {
+            query.neighbors = neighbors;
+        }
+        if let Some(scope) = args.scope {
+            query.scope = scope;
+        }
+        if let Some(aggregate) = args.aggregate {
+            query.aggregate = aggregate;
+        }
+
+        Ok(Config {
+            input,
+            symbols: args.symbols.unwrap_or_default(),
+            adjacency: args.adjacency.unwrap_or_default(),
+            query,
+            render: args.render.map(|style| style.unwrap_or_else(Style::detect)),
+            graph: args.graph.map(Option::unwrap_or_default),
+        })
     }
 }
 
@@ -48,95 +125,154 @@ pub fn run(cfg: Config) -> anyhow::Result<()> {
             io::stdin().read_line(&mut buf)?;
             buf
         }
+        InputConfig::Batch(dir, timeout) => {
+            let report = batch::run_batch(&dir, timeout, move |input| {
+                solve(input, &cfg.symbols, cfg.adjacency, &cfg.query)
+            })?;
+            println!("{}", report);
+            if report.failures() > 0 {
+                anyhow::bail!(
+                    "{} of {} inputs failed",
+                    report.failures(),
+                    report.results.len()
+                );
+            }
+            return Ok(());
+        }
     };
 
-    let result = process(input_string)?;
+    if let Some(style) = cfg.render {
+        print!(
+            "{}",
+            Schematic::parse(&input_string, &cfg.symbols)?
+                .with_adjacency(cfg.adjacency)
+                .render(style, &cfg.query)
+        );
+    }
+    // the graph has stdout to itself so it can be piped straight into other tools
+    if let Some(format) = cfg.graph {
+        let graph = Schematic::parse(&input_string, &cfg.symbols)?
+            .with_adjacency(cfg.adjacency)
+            .graph();
+        println!("{}", graph.write(format)?.trim_end());
+        eprintln!(
+            "total: {}",
+            solve(input_string, &cfg.symbols, cfg.adjacency, &cfg.query)?
+        );
+        return Ok(());
+    }
+
+    let result = solve(input_string, &cfg.symbols, cfg.adjacency, &cfg.query)?;
 
     println!("{}", result);
 
     Ok(())
 }
 
-// seperate input into lines, find numbers, and find gears
-// then for each number, increment the count for any gears it's close to
-// add up all gears
-fn process(input: String) -> anyhow::Result<u32> {
-    // offsets for finding neighbors of gears
-    const OFFSETS: [(i32, i32); 8] = [
-        (0, 1),
-        (0, -1),
-        (1, 1),
-        (1, -1),
-        (1, 0),
-        (-1, 1),
-        (-1, -1),
-        (-1, 0),
-    ];
-    // regex for finding numbers
-    let num_pattern = Regex::new(r"\d+").unwrap();
-    // regex for finding potential gears
-    let pot_gear_pattern = Regex::new(r"\*").unwrap();
-
-    // list of potential gears (locations with *s)
-    let mut potential_gears = Vec::new();
-
-    let linecount = input.lines().count() as i32; // used to determine if a neighbor is out of bounds
-
-    // find gears and record their neighbors
-    for (lineno, line) in input.lines().enumerate() {
-        for gear_match in pot_gear_pattern.find_iter(line) {
-            let mut neighbors = HashSet::new();
-            for offset in OFFSETS {
-                let neighbor_line = lineno as i32 + offset.0;
-                let neighbor_col = gear_match.start() as i32 + offset.1;
-                if 0 <= neighbor_line
-                    && neighbor_line < linecount
-                    && 0 <= neighbor_col
-                    && neighbor_col < line.len() as i32
-                {
-                    neighbors.insert((neighbor_line as usize, neighbor_col as usize));
-                }
-            }
+/// parse and process a single input
+fn solve(
+    input: String,
+    symbols: &SymbolSet,
+    adjacency: Adjacency,
+    query: &Query,
+) -> anyhow::Result<u128> {
+    process(input, symbols, adjacency, query)
+}
 
-            potential_gears.push(PotentialGear {
-                neighbors,
-                neighboring_nums: 0,
-                ratio: 1,
-            });
-        }
+// index the numbers and symbols, then answer the query (the sum of every gear ratio unless told otherwise)
+fn process(
+    input: String,
+    symbols: &SymbolSet,
+    adjacency: Adjacency,
+    query: &Query,
+) -> anyhow::Result<u128> {
+    let schematic =
+        alloc::measure("parse", || Schematic::parse(&input, symbols))?.with_adjacency(adjacency);
+    alloc::measure("solve", || schematic.query(query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoccommon::schematic::LiveSchematic;
+
+    const EXAMPLE: &str = "\
+467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..
+";
+
+    fn answer(adjacency: &str) -> u128 {
+        let query = Query::gear_ratios();
+        let adjacency = adjacency.parse().unwrap();
+        process(EXAMPLE.to_string(), &SymbolSet::Default, adjacency, &query).unwrap()
     }
 
-    // for each gear, check each number to see if its a neighbor
-    // if it is, increment that gear's counters
-    for (lineno, line) in input.lines().enumerate() {
-        let num_matches = num_pattern.find_iter(line).collect::<Vec<_>>();
-        for pot_gear in potential_gears.iter_mut() {
-            'num_loop: for num_match in num_matches.iter() {
-                for colno in num_match.range() {
-                    if pot_gear.neighbors.contains(&(lineno, colno)) {
-                        pot_gear.neighboring_nums += 1;
-                        if pot_gear.neighboring_nums < 3 {
-                            pot_gear.ratio *= num_match.as_str().parse::<u32>().unwrap();
-                        }
-                        // don't double count the same number on a single gear (once for each digit)
-                        continue 'num_loop;
-                    }
-                }
-            }
+    #[test]
+    fn example_gear_ratios_by_adjacency() {
+        // each gear has one number across a corner, so orthogonal leaves neither with two,
+        // and a reach of two rows and columns gives both of them a third number
+        for (adjacency, expected) in [
+            ("around", 467835),
+            ("orthogonal", 0),
+            ("diagonal", 467835),
+            ("chebyshev:2", 0),
+            ("manhattan:2", 467835),
+            ("chebyshev:0", 0),
+        ] {
+            assert_eq!(answer(adjacency), expected, "{}", adjacency);
         }
     }
 
-    // find actual gears (potential gears with only 2 numbers)
-    Ok(potential_gears
-        .into_iter()
-        .filter(|gear| gear.neighboring_nums == 2)
-        .map(|gear| gear.ratio)
-        .sum())
-}
+    #[test]
+    fn live_edits_match_a_full_recompute() {
+        // a small linear congruential generator keeps the edits random but repeatable
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move |n: usize| {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 33) as usize % n
+        };
+        let cells = [
+            '.', '.', '.', '.', '.', '1', '2', '5', '9', '0', '*', '*', '#', '日', '\t',
+        ];
 
-#[derive(Debug)]
-struct PotentialGear {
-    neighbors: HashSet<(usize, usize)>,
-    neighboring_nums: u32,
-    ratio: u32,
+        for adjacency in [
+            "around",
+            "orthogonal",
+            "diagonal",
+            "manhattan:2",
+            "chebyshev:2",
+        ] {
+            let adjacency = adjacency.parse().unwrap();
+            let mut live = LiveSchematic::new(EXAMPLE, SymbolSet::Default, adjacency).unwrap();
+            for _ in 0..400 {
+                // a little past the example's edges so rows and columns grow too
+                let pos = (next(13), next(13));
+                live.set(pos, cells[next(cells.len())]).unwrap();
+                let full = process(
+                    live.to_string(),
+                    &SymbolSet::Default,
+                    adjacency,
+                    &Query::gear_ratios(),
+                )
+                .unwrap();
+                assert_eq!(
+                    live.gear_sum(),
+                    full,
+                    "{:?} after editing\n{}",
+                    adjacency,
+                    live
+                );
+            }
+        }
+    }
 }
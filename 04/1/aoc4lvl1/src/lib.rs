@@ -1,37 +1,57 @@
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
 extern crate clap;
 use clap::Parser;
 
 extern crate anyhow;
 
+extern crate aoccommon;
+use aoccommon::alloc;
+use aoccommon::batch;
+use aoccommon::config::AocConfig;
+use aoccommon::parsing;
+
 #[derive(Parser, Debug)]
 pub struct Args {
     /// path to the input file
     #[arg(short, long)]
     input: Option<PathBuf>,
+    /// path to a directory of input files to solve in parallel
+    #[arg(long, conflicts_with = "input")]
+    inputs: Option<PathBuf>,
 }
 
 enum InputConfig {
     File(PathBuf),
     Stdin,
+    Batch(PathBuf, Option<Duration>),
 }
 pub struct Config {
     input: InputConfig,
 }
 
 impl Config {
-    pub fn make() -> Self {
+    pub fn make() -> anyhow::Result<Self> {
         let args = Args::parse();
-        let input = if let Some(path) = args.input {
+        let file_cfg = AocConfig::load()?;
+        // command line input wins over the config file
+        let input = if let Some(dir) = args.inputs {
+            InputConfig::Batch(dir, file_cfg.timeout())
+        } else if let Some(path) = args
+            .input
+            .map(Ok)
+            .or_else(|| file_cfg.input_path(4).transpose())
+            .transpose()?
+        {
             InputConfig::File(path)
         } else {
             InputConfig::Stdin
         };
 
-        Config { input }
+        Ok(Config { input })
     }
 }
 
@@ -44,35 +64,47 @@ pub fn run(cfg: Config) -> anyhow::Result<()> {
             io::stdin().read_line(&mut buf)?;
             buf
         }
+        InputConfig::Batch(dir, timeout) => {
+            let report = batch::run_batch(&dir, timeout, solve)?;
+            println!("{}", report);
+            if report.failures() > 0 {
+                anyhow::bail!(
+                    "{} of {} inputs failed",
+                    report.failures(),
+                    report.results.len()
+                );
+            }
+            return Ok(());
+        }
     };
 
-    let data = parse(&input_string)?;
-    let result = process(data);
+    let result = solve(input_string)?;
 
     println!("{}", result);
 
     Ok(())
 }
 
+/// parse and process a single input
+fn solve(input: String) -> anyhow::Result<u32> {
+    let data = alloc::measure("parse", || parse(&input))?;
+    Ok(alloc::measure("solve", || process(data)))
+}
+
 /// given our input, seperate it into cards with winning numbers and numbers we have
 fn parse(input: &str) -> anyhow::Result<Vec<Card>> {
-    Ok(input
-        // split into lines
-        .lines()
-        // remove card identifier info
-        .map(|line| &line[line.find(':').unwrap() + 1..])
-        // split into winning numbers and have numbers
-        .map(|line| line.split('|'))
-        // split up numbes and put them on a card
-        .map(|mut nums| {
-            let winning_nums = nums.next().unwrap().split_whitespace().collect::<Vec<_>>();
-            let have_nums = nums.next().unwrap().split_whitespace().collect::<Vec<_>>();
-            Card {
-                winning_nums,
-                have_nums,
-            }
+    parsing::lines(input)
+        .map(|line| {
+            // remove card identifier info
+            let (_, nums) = parsing::numbered_record(line, "Card")?;
+            // split into winning numbers and have numbers
+            let (winning_nums, have_nums) = nums.split_once("|")?;
+            Ok(Card {
+                winning_nums: winning_nums.numbers("an unsigned integer")?,
+                have_nums: have_nums.numbers("an unsigned integer")?,
+            })
         })
-        .collect())
+        .collect()
 }
 
 /// given a vector of cards, calculate the total score
@@ -94,7 +126,36 @@ fn process(cards: Vec<Card>) -> u32 {
     total_score
 }
 
-struct Card<'a> {
-    winning_nums: Vec<&'a str>,
-    have_nums: Vec<&'a str>,
+struct Card {
+    winning_nums: Vec<u32>,
+    have_nums: Vec<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11
+";
+
+    #[test]
+    fn example() {
+        assert_eq!(solve(EXAMPLE.to_string()).unwrap(), 13);
+    }
+
+    #[test]
+    fn errors_say_where() {
+        let err = solve("Card 1: 41 48 | 83 x6".to_string()).unwrap_err();
+        assert!(
+            err.to_string().starts_with("line 1, column 20: "),
+            "{}",
+            err
+        );
+    }
 }
@@ -2,37 +2,57 @@ use std::collections::HashMap;
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
 extern crate clap;
 use clap::Parser;
 
 extern crate anyhow;
 
+extern crate aoccommon;
+use aoccommon::alloc;
+use aoccommon::batch;
+use aoccommon::config::AocConfig;
+use aoccommon::parsing;
+
 #[derive(Parser, Debug)]
 pub struct Args {
     /// path to the input file
     #[arg(short, long)]
     input: Option<PathBuf>,
+    /// path to a directory of input files to solve in parallel
+    #[arg(long, conflicts_with = "input")]
+    inputs: Option<PathBuf>,
 }
 
 enum InputConfig {
     File(PathBuf),
     Stdin,
+    Batch(PathBuf, Option<Duration>),
 }
 pub struct Config {
     input: InputConfig,
 }
 
 impl Config {
-    pub fn make() -> Self {
+    pub fn make() -> anyhow::Result<Self> {
         let args = Args::parse();
-        let input = if let Some(path) = args.input {
+        let file_cfg = AocConfig::load()?;
+        // command line input wins over the config file
+        let input = if let Some(dir) = args.inputs {
+            InputConfig::Batch(dir, file_cfg.timeout())
+        } else if let Some(path) = args
+            .input
+            .map(Ok)
+            .or_else(|| file_cfg.input_path(5).transpose())
+            .transpose()?
+        {
             InputConfig::File(path)
         } else {
             InputConfig::Stdin
         };
 
-        Config { input }
+        Ok(Config { input })
     }
 }
 
@@ -45,73 +65,79 @@ pub fn run(cfg: Config) -> anyhow::Result<()> {
             io::stdin().read_line(&mut buf)?;
             buf
         }
+        InputConfig::Batch(dir, timeout) => {
+            let report = batch::run_batch(&dir, timeout, solve)?;
+            println!("{}", report);
+            if report.failures() > 0 {
+                anyhow::bail!(
+                    "{} of {} inputs failed",
+                    report.failures(),
+                    report.results.len()
+                );
+            }
+            return Ok(());
+        }
     };
 
-    let (seeds, maps) = parse(input_string)?;
-    let result = process(seeds, maps);
+    let result = solve(input_string)?;
 
     println!("{}", result);
 
     Ok(())
 }
 
+/// parse and process a single input
+fn solve(input: String) -> anyhow::Result<u64> {
+    let (seeds, maps) = alloc::measure("parse", || parse(input))?;
+    Ok(alloc::measure("solve", || process(seeds, maps)))
+}
+
 /// given our input string, parse it into seeds and resource maps
 fn parse(input: String) -> anyhow::Result<(Vec<Resource>, HashMap<String, ResourceMap>)> {
-    let mut sections = input.split("\n\n");
+    let mut sections = parsing::sections(&input).into_iter();
 
     // feeling like a real rustacean now bb
-    let seeds = sections
+    let seed_section = sections
         .next()
-        .ok_or(anyhow::anyhow!("input missing seed section"))?
-        .split_whitespace()
-        // skip seed label
-        .skip(1)
-        // I still don't know how to get results out of iterators
-        .map(|seed_str| seed_str.parse::<u64>().unwrap())
+        .ok_or(anyhow::anyhow!("input missing seed section"))?;
+    let seeds = parsing::labelled_numbers::<u64>(seed_section[0], "seeds", "an unsigned integer")?
+        .into_iter()
         .map(|seed_id| Resource {
             name: "seed".to_string(),
             id: seed_id,
         })
         .collect::<Vec<_>>();
 
-    // really gotta figure out throwing errors from closures
     let maps = sections
-        .map(|section| {
-            let mut lines = section.lines();
-
+        .map(|section| -> parsing::Result<(String, ResourceMap)> {
             // what resources are this map converting to/from
-            let resource_map_label = lines.next().unwrap();
-            let mut resource_name_iter =
-                resource_map_label[..resource_map_label.len() - 1].split("-to-");
-            let from = resource_name_iter.next().unwrap().to_string();
-            let to = resource_name_iter
-                .next()
-                .unwrap()
-                .split_whitespace()
-                .next()
-                .unwrap()
-                .to_string();
+            let (from, to) = section[0]
+                .expect_suffix("map:")?
+                .trim()
+                .split_once("-to-")?;
 
             // convert remaining lines to ranges
-            let ranges = lines
+            let ranges = section[1..]
+                .iter()
                 .map(|line| {
-                    let mut line_iter = line.split_whitespace();
-                    let to_start = line_iter.next().unwrap().parse::<u64>().unwrap();
-                    let from_start = line_iter.next().unwrap().parse::<u64>().unwrap();
-                    let length = line_iter.next().unwrap().parse::<u64>().unwrap();
-
-                    ResourceRange {
+                    let [to_start, from_start, length] =
+                        line.fixed_numbers::<u64, 3>("an unsigned integer")?;
+                    Ok(ResourceRange {
                         from_start,
                         to_start,
                         length,
-                    }
+                    })
                 })
-                .collect::<Vec<_>>();
+                .collect::<parsing::Result<Vec<_>>>()?;
 
-            ResourceMap { from, to, ranges }
+            let map = ResourceMap {
+                from: from.as_str().to_string(),
+                to: to.as_str().to_string(),
+                ranges,
+            };
+            Ok((map.from.to_string(), map))
         })
-        .map(|map| (map.from.to_string(), map))
-        .collect::<HashMap<_, _>>();
+        .collect::<parsing::Result<HashMap<_, _>>>()?;
 
     Ok((seeds, maps))
 }
@@ -188,3 +214,49 @@ impl ResourceRange {
         from_id - self.from_start + self.to_start
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4
+";
+
+    #[test]
+    fn example() {
+        assert_eq!(solve(EXAMPLE.to_string()).unwrap(), 35);
+    }
+}
@@ -2,37 +2,57 @@ use std::fs;
 use std::io;
 use std::iter;
 use std::path::PathBuf;
+use std::time::Duration;
 
 extern crate clap;
 use clap::Parser;
 
 extern crate anyhow;
 
+extern crate aoccommon;
+use aoccommon::alloc;
+use aoccommon::batch;
+use aoccommon::config::AocConfig;
+use aoccommon::parsing;
+
 #[derive(Parser, Debug)]
 pub struct Args {
     /// path to the input file
     #[arg(short, long)]
     input: Option<PathBuf>,
+    /// path to a directory of input files to solve in parallel
+    #[arg(long, conflicts_with = "input")]
+    inputs: Option<PathBuf>,
 }
 
 enum InputConfig {
     File(PathBuf),
     Stdin,
+    Batch(PathBuf, Option<Duration>),
 }
 pub struct Config {
     input: InputConfig,
 }
 
 impl Config {
-    pub fn make() -> Self {
+    pub fn make() -> anyhow::Result<Self> {
         let args = Args::parse();
-        let input = if let Some(path) = args.input {
+        let file_cfg = AocConfig::load()?;
+        // command line input wins over the config file
+        let input = if let Some(dir) = args.inputs {
+            InputConfig::Batch(dir, file_cfg.timeout())
+        } else if let Some(path) = args
+            .input
+            .map(Ok)
+            .or_else(|| file_cfg.input_path(6).transpose())
+            .transpose()?
+        {
             InputConfig::File(path)
         } else {
             InputConfig::Stdin
         };
 
-        Config { input }
+        Ok(Config { input })
     }
 }
 
@@ -45,20 +65,37 @@ pub fn run(cfg: Config) -> anyhow::Result<()> {
             io::stdin().read_line(&mut buf)?;
             buf
         }
+        InputConfig::Batch(dir, timeout) => {
+            let report = batch::run_batch(&dir, timeout, solve)?;
+            println!("{}", report);
+            if report.failures() > 0 {
+                anyhow::bail!(
+                    "{} of {} inputs failed",
+                    report.failures(),
+                    report.results.len()
+                );
+            }
+            return Ok(());
+        }
     };
 
-    let data = parse(input_string)?;
-    let result = process(data);
+    let result = solve(input_string)?;
 
     println!("{}", result);
 
     Ok(())
 }
 
+/// parse and process a single input
+fn solve(input: String) -> anyhow::Result<u32> {
+    let data = alloc::measure("parse", || parse(input))?;
+    Ok(alloc::measure("solve", || process(data)))
+}
+
 /// parse the input data into races
 fn parse(input: String) -> anyhow::Result<Vec<Race>> {
     // seperate lines
-    let mut lines_iter = input.lines();
+    let mut lines_iter = parsing::lines(&input);
     let time_line = lines_iter
         .next()
         .ok_or(anyhow::anyhow!("Missing first line in file"))?;
@@ -66,17 +103,22 @@ fn parse(input: String) -> anyhow::Result<Vec<Race>> {
         .next()
         .ok_or(anyhow::anyhow!("Missing second line in file"))?;
 
+    let times = parsing::labelled_numbers::<f32>(time_line, "Time", "a number")?;
+    let distances = parsing::labelled_numbers::<f32>(dist_line, "Distance", "a number")?;
+    if times.len() != distances.len() {
+        return Err(dist_line
+            .error(format!(
+                "expected {} distances to match the times, found {}",
+                times.len(),
+                distances.len()
+            ))
+            .into());
+    }
+
     // associate values and make race structs
-    // man I still cannot figure out how to throw errors from closures
-    Ok(
-        iter::zip(time_line.split_whitespace(), dist_line.split_whitespace())
-            .skip(1)
-            .map(|(t, d)| Race {
-                time: t.parse::<f32>().expect("failed to parse number"),
-                distance: d.parse::<f32>().expect("failed to parse number"),
-            })
-            .collect(),
-    )
+    Ok(iter::zip(times, distances)
+        .map(|(time, distance)| Race { time, distance })
+        .collect())
 }
 
 fn process(races: Vec<Race>) -> u32 {
@@ -85,7 +127,7 @@ fn process(races: Vec<Race>) -> u32 {
         .map(|race| {
             // we're using the quadratic formula to find the two roots, then counting the integers between
             let sqrt_term = (race.time * race.time - 4_f32 * race.distance).sqrt();
-            let b_term = -1_f32 * race.time;
+            let b_term = -race.time;
             let a_term = -2_f32;
 
             let lower_root = (b_term + sqrt_term) / a_term;
@@ -100,3 +142,28 @@ struct Race {
     time: f32,
     distance: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Time:      7  15   30
+Distance:  9  40  200
+";
+
+    #[test]
+    fn example() {
+        assert_eq!(solve(EXAMPLE.to_string()).unwrap(), 288);
+    }
+
+    #[test]
+    fn errors_say_where() {
+        let err = solve("Time: 7 15\nDistance: 9 x0\n".to_string()).unwrap_err();
+        assert!(
+            err.to_string().starts_with("line 2, column 13: "),
+            "{}",
+            err
+        );
+    }
+}
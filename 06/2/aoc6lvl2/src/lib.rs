@@ -1,37 +1,57 @@
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
 extern crate clap;
 use clap::Parser;
 
 extern crate anyhow;
 
+extern crate aoccommon;
+use aoccommon::alloc;
+use aoccommon::batch;
+use aoccommon::config::AocConfig;
+use aoccommon::parsing;
+
 #[derive(Parser, Debug)]
 pub struct Args {
     /// path to the input file
     #[arg(short, long)]
     input: Option<PathBuf>,
+    /// path to a directory of input files to solve in parallel
+    #[arg(long, conflicts_with = "input")]
+    inputs: Option<PathBuf>,
 }
 
 enum InputConfig {
     File(PathBuf),
     Stdin,
+    Batch(PathBuf, Option<Duration>),
 }
 pub struct Config {
     input: InputConfig,
 }
 
 impl Config {
-    pub fn make() -> Self {
+    pub fn make() -> anyhow::Result<Self> {
         let args = Args::parse();
-        let input = if let Some(path) = args.input {
+        let file_cfg = AocConfig::load()?;
+        // command line input wins over the config file
+        let input = if let Some(dir) = args.inputs {
+            InputConfig::Batch(dir, file_cfg.timeout())
+        } else if let Some(path) = args
+            .input
+            .map(Ok)
+            .or_else(|| file_cfg.input_path(6).transpose())
+            .transpose()?
+        {
             InputConfig::File(path)
         } else {
             InputConfig::Stdin
         };
 
-        Config { input }
+        Ok(Config { input })
     }
 }
 
@@ -44,50 +64,66 @@ pub fn run(cfg: Config) -> anyhow::Result<()> {
             io::stdin().read_line(&mut buf)?;
             buf
         }
+        InputConfig::Batch(dir, timeout) => {
+            let report = batch::run_batch(&dir, timeout, solve)?;
+            println!("{}", report);
+            if report.failures() > 0 {
+                anyhow::bail!(
+                    "{} of {} inputs failed",
+                    report.failures(),
+                    report.results.len()
+                );
+            }
+            return Ok(());
+        }
     };
 
-    let data = parse(input_string)?;
-    let result = process(data);
+    let result = solve(input_string)?;
 
     println!("{}", result);
 
     Ok(())
 }
 
+/// parse and process a single input
+fn solve(input: String) -> anyhow::Result<u32> {
+    let data = alloc::measure("parse", || parse(input))?;
+    Ok(alloc::measure("solve", || process(data)))
+}
+
 /// parse the input data into a single race
 fn parse(input: String) -> anyhow::Result<Race> {
     // seperate lines
-    let mut lines_iter = input.lines();
-
-    // for each line, skip the label and shove the characters together
-    let time = lines_iter
+    let mut lines_iter = parsing::lines(&input);
+    let time_line = lines_iter
         .next()
-        .ok_or(anyhow::anyhow!("Missing first line in file"))?
-        .split_whitespace()
-        .skip(1)
-        .fold(String::new(), |mut acc, s| {
-            acc.push_str(s);
-            acc
-        })
-        .parse::<f64>()?;
-    let distance = lines_iter
+        .ok_or(anyhow::anyhow!("Missing first line in file"))?;
+    let dist_line = lines_iter
         .next()
-        .ok_or(anyhow::anyhow!("Missing second line in file"))?
-        .split_whitespace()
-        .skip(1)
-        .fold(String::new(), |mut acc, s| {
-            acc.push_str(s);
-            acc
-        })
-        .parse::<f64>()?;
+        .ok_or(anyhow::anyhow!("Missing second line in file"))?;
+
+    let time = parse_kerned_number(time_line, "Time")?;
+    let distance = parse_kerned_number(dist_line, "Distance")?;
 
     Ok(Race { time, distance })
 }
 
+/// skip the label and shove the characters together into one number
+fn parse_kerned_number(line: parsing::Span, label: &str) -> parsing::Result<f64> {
+    let digits = line.label(label)?;
+    let joined = digits.words().fold(String::new(), |mut acc, s| {
+        acc.push_str(s.as_str());
+        acc
+    });
+    joined
+        .parse::<f64>()
+        .map_err(|_| digits.error(format!("expected digits, found {:?}", digits.as_str())))
+}
+
 fn process(race: Race) -> u32 {
     // we're using the quadratic formula to find the two roots, then counting the integers between
     let sqrt_term = (race.time * race.time - 4_f64 * race.distance).sqrt();
-    let b_term = -1_f64 * race.time;
+    let b_term = -race.time;
     let a_term = -2_f64;
 
     let lower_root = (b_term + sqrt_term) / a_term;
@@ -100,3 +136,18 @@ struct Race {
     time: f64,
     distance: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Time:      7  15   30
+Distance:  9  40  200
+";
+
+    #[test]
+    fn example() {
+        assert_eq!(solve(EXAMPLE.to_string()).unwrap(), 71503);
+    }
+}
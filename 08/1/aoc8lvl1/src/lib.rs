@@ -2,37 +2,70 @@ use std::collections::HashMap;
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
 extern crate clap;
 use clap::Parser;
 
 extern crate anyhow;
 
+extern crate aoccommon;
+use aoccommon::alloc;
+use aoccommon::batch;
+use aoccommon::config::AocConfig;
+use aoccommon::parsing;
+
 #[derive(Parser, Debug)]
 pub struct Args {
     /// path to the input file
     #[arg(short, long)]
     input: Option<PathBuf>,
+    /// path to a directory of input files to solve in parallel
+    #[arg(long, conflicts_with = "input")]
+    inputs: Option<PathBuf>,
 }
 
 enum InputConfig {
     File(PathBuf),
     Stdin,
+    Batch(PathBuf, Option<Duration>),
 }
 pub struct Config {
     input: InputConfig,
+    start: String,
+    target: String,
 }
 
 impl Config {
-    pub fn make() -> Self {
+    pub fn make() -> anyhow::Result<Self> {
         let args = Args::parse();
-        let input = if let Some(path) = args.input {
+        let file_cfg = AocConfig::load()?;
+        // command line input wins over the config file
+        let input = if let Some(dir) = args.inputs {
+            InputConfig::Batch(dir, file_cfg.timeout())
+        } else if let Some(path) = args
+            .input
+            .map(Ok)
+            .or_else(|| file_cfg.input_path(8).transpose())
+            .transpose()?
+        {
             InputConfig::File(path)
         } else {
             InputConfig::Stdin
         };
 
-        Config { input }
+        let start = file_cfg
+            .day_param(8, "start")?
+            .unwrap_or_else(|| "AAA".to_string());
+        let target = file_cfg
+            .day_param(8, "target")?
+            .unwrap_or_else(|| "ZZZ".to_string());
+
+        Ok(Config {
+            input,
+            start,
+            target,
+        })
     }
 }
 
@@ -45,60 +78,97 @@ pub fn run(cfg: Config) -> anyhow::Result<()> {
             io::stdin().read_line(&mut buf)?;
             buf
         }
+        InputConfig::Batch(dir, timeout) => {
+            let report = batch::run_batch(&dir, timeout, move |input| {
+                solve(input, &cfg.start, &cfg.target)
+            })?;
+            println!("{}", report);
+            if report.failures() > 0 {
+                anyhow::bail!(
+                    "{} of {} inputs failed",
+                    report.failures(),
+                    report.results.len()
+                );
+            }
+            return Ok(());
+        }
     };
 
-    let (directions, graph) = parse(input_string)?;
-    let result = process(directions, graph);
+    let result = solve(input_string, &cfg.start, &cfg.target)?;
 
     println!("{}", result);
 
     Ok(())
 }
 
+/// parse and process a single input
+fn solve(input: String, start: &str, target: &str) -> anyhow::Result<u32> {
+    let (directions, graph) = alloc::measure("parse", || parse(input))?;
+    Ok(alloc::measure("solve", || {
+        process(directions, graph, start, target)
+    }))
+}
+
 /// parse input into a vector of directions and a hashmap associating labels with nodes
 fn parse(input: String) -> anyhow::Result<(Vec<Direction>, HashMap<String, Node>)> {
     // graphs in rust are hard but this one isn't awful yet
-    let mut line_iter = input.lines();
+    let mut line_iter = parsing::lines(&input);
     // parse directions
     let direction_line = line_iter
         .next()
         .ok_or(anyhow::anyhow!("empty input file"))?;
     let directions = direction_line
+        .trim()
         .chars()
-        .map(|c| match c {
-            'L' => Direction::Left,
-            'R' => Direction::Right,
-            _ => panic!(
-                "invalid character in direction list; I still can't return errors from closures"
-            ),
+        .map(|(c, span)| match c {
+            'L' => Ok(Direction::Left),
+            'R' => Ok(Direction::Right),
+            _ => Err(span.error(format!("invalid character {:?} in direction list", c))),
         })
-        .collect::<Vec<Direction>>();
+        .collect::<parsing::Result<Vec<Direction>>>()?;
 
     // skip blank line
-    line_iter
+    let blank_line = line_iter
         .next()
         .ok_or(anyhow::anyhow!("missing line after input line"))?;
+    if !blank_line.trim().is_empty() {
+        return Err(blank_line
+            .error("expected a blank line after the directions")
+            .into());
+    }
 
-    // contstruct graph by taking character indices
+    // contstruct graph from lines like AAA = (BBB, CCC)
     let graph = line_iter
         .map(|line| {
-            let label = line[0..=2].to_string();
-            let left = line[7..=9].to_string();
-            let right = line[12..=14].to_string();
-            Node { label, left, right }
+            let (label, neighbors) = parsing::named_tuple(line)?;
+            let [left, right] = neighbors[..] else {
+                return Err(
+                    line.error(format!("expected two neighbors, found {}", neighbors.len()))
+                );
+            };
+            Ok(Node {
+                label: label.as_str().to_string(),
+                left: left.as_str().to_string(),
+                right: right.as_str().to_string(),
+            })
         })
-        .map(|node| (node.label.clone(), node))
-        .collect::<HashMap<String, Node>>();
+        .map(|node| node.map(|node| (node.label.clone(), node)))
+        .collect::<parsing::Result<HashMap<String, Node>>>()?;
 
     Ok((directions, graph))
 }
 
-fn process(directions: Vec<Direction>, graph: HashMap<String, Node>) -> u32 {
-    let mut cur_node = graph.get("AAA").expect("invalid graph");
+fn process(
+    directions: Vec<Direction>,
+    graph: HashMap<String, Node>,
+    start: &str,
+    target: &str,
+) -> u32 {
+    let mut cur_node = graph.get(start).expect("invalid graph");
     let mut direction_iter = directions.into_iter().cycle();
     let mut num_steps = 0;
     // traverse graph until we find our target, counting steps
-    while cur_node.label != "ZZZ" {
+    while cur_node.label != target {
         cur_node = match direction_iter.next().unwrap() {
             Direction::Left => graph.get(&cur_node.left).expect("Missing node in graph"),
             Direction::Right => graph.get(&cur_node.right).expect("Missing node in graph"),
@@ -120,3 +190,25 @@ struct Node {
     left: String,
     right: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+RL
+
+AAA = (BBB, CCC)
+BBB = (DDD, EEE)
+CCC = (ZZZ, GGG)
+DDD = (DDD, DDD)
+EEE = (EEE, EEE)
+GGG = (GGG, GGG)
+ZZZ = (ZZZ, ZZZ)
+";
+
+    #[test]
+    fn example() {
+        assert_eq!(solve(EXAMPLE.to_string(), "AAA", "ZZZ").unwrap(), 2);
+    }
+}
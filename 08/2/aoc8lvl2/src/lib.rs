@@ -2,37 +2,71 @@ use std::collections::HashMap;
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
 extern crate clap;
 use clap::Parser;
 
 extern crate anyhow;
 
+extern crate aoccommon;
+use aoccommon::alloc;
+use aoccommon::batch;
+use aoccommon::config::AocConfig;
+use aoccommon::parsing;
+
 #[derive(Parser, Debug)]
 pub struct Args {
     /// path to the input file
     #[arg(short, long)]
     input: Option<PathBuf>,
+    /// path to a directory of input files to solve in parallel
+    #[arg(long, conflicts_with = "input")]
+    inputs: Option<PathBuf>,
 }
 
 enum InputConfig {
     File(PathBuf),
     Stdin,
+    Batch(PathBuf, Option<Duration>),
 }
 pub struct Config {
     input: InputConfig,
+    start_suffix: char,
+    target_suffix: char,
+    progress_interval: u64,
 }
 
 impl Config {
-    pub fn make() -> Self {
+    pub fn make() -> anyhow::Result<Self> {
         let args = Args::parse();
-        let input = if let Some(path) = args.input {
+        let file_cfg = AocConfig::load()?;
+        // command line input wins over the config file
+        let input = if let Some(dir) = args.inputs {
+            InputConfig::Batch(dir, file_cfg.timeout())
+        } else if let Some(path) = args
+            .input
+            .map(Ok)
+            .or_else(|| file_cfg.input_path(8).transpose())
+            .transpose()?
+        {
             InputConfig::File(path)
         } else {
             InputConfig::Stdin
         };
 
-        Config { input }
+        let start_suffix = file_cfg.day_param(8, "start_suffix")?.unwrap_or('A');
+        let target_suffix = file_cfg.day_param(8, "target_suffix")?.unwrap_or('Z');
+        let progress_interval = file_cfg
+            .day_param(8, "progress_interval")?
+            .unwrap_or(10000000);
+
+        Ok(Config {
+            input,
+            start_suffix,
+            target_suffix,
+            progress_interval,
+        })
     }
 }
 
@@ -45,59 +79,118 @@ pub fn run(cfg: Config) -> anyhow::Result<()> {
             io::stdin().read_line(&mut buf)?;
             buf
         }
+        InputConfig::Batch(dir, timeout) => {
+            let report = batch::run_batch(&dir, timeout, move |input| {
+                solve(
+                    input,
+                    cfg.start_suffix,
+                    cfg.target_suffix,
+                    cfg.progress_interval,
+                )
+            })?;
+            println!("{}", report);
+            if report.failures() > 0 {
+                anyhow::bail!(
+                    "{} of {} inputs failed",
+                    report.failures(),
+                    report.results.len()
+                );
+            }
+            return Ok(());
+        }
     };
 
-    let (directions, graph) = parse(input_string)?;
-    let result = process(directions, graph);
+    let result = solve(
+        input_string,
+        cfg.start_suffix,
+        cfg.target_suffix,
+        cfg.progress_interval,
+    )?;
 
     println!("{}", result);
 
     Ok(())
 }
 
+/// parse and process a single input
+fn solve(
+    input: String,
+    start_suffix: char,
+    target_suffix: char,
+    progress_interval: u64,
+) -> anyhow::Result<u64> {
+    let (directions, graph) = alloc::measure("parse", || parse(input))?;
+    Ok(alloc::measure("solve", || {
+        process(
+            directions,
+            graph,
+            start_suffix,
+            target_suffix,
+            progress_interval,
+        )
+    }))
+}
+
 /// parse input into a vector of directions and a hashmap associating labels with nodes
 fn parse(input: String) -> anyhow::Result<(Vec<Direction>, HashMap<String, Node>)> {
     // graphs in rust are hard but this one isn't awful yet
-    let mut line_iter = input.lines();
+    let mut line_iter = parsing::lines(&input);
     // parse directions
     let direction_line = line_iter
         .next()
         .ok_or(anyhow::anyhow!("empty input file"))?;
     let directions = direction_line
+        .trim()
         .chars()
-        .map(|c| match c {
-            'L' => Direction::Left,
-            'R' => Direction::Right,
-            _ => panic!(
-                "invalid character in direction list; I still can't return errors from closures"
-            ),
+        .map(|(c, span)| match c {
+            'L' => Ok(Direction::Left),
+            'R' => Ok(Direction::Right),
+            _ => Err(span.error(format!("invalid character {:?} in direction list", c))),
         })
-        .collect::<Vec<Direction>>();
+        .collect::<parsing::Result<Vec<Direction>>>()?;
 
     // skip blank line
-    line_iter
+    let blank_line = line_iter
         .next()
         .ok_or(anyhow::anyhow!("missing line after input line"))?;
+    if !blank_line.trim().is_empty() {
+        return Err(blank_line
+            .error("expected a blank line after the directions")
+            .into());
+    }
 
-    // contstruct graph by taking character indices
+    // contstruct graph from lines like AAA = (BBB, CCC)
     let graph = line_iter
         .map(|line| {
-            let label = line[0..=2].to_string();
-            let left = line[7..=9].to_string();
-            let right = line[12..=14].to_string();
-            Node { label, left, right }
+            let (label, neighbors) = parsing::named_tuple(line)?;
+            let [left, right] = neighbors[..] else {
+                return Err(
+                    line.error(format!("expected two neighbors, found {}", neighbors.len()))
+                );
+            };
+            Ok(Node {
+                label: label.as_str().to_string(),
+                left: left.as_str().to_string(),
+                right: right.as_str().to_string(),
+            })
         })
-        .map(|node| (node.label.clone(), node))
-        .collect::<HashMap<String, Node>>();
+        .map(|node| node.map(|node| (node.label.clone(), node)))
+        .collect::<parsing::Result<HashMap<String, Node>>>()?;
 
     Ok((directions, graph))
 }
 
-fn process(directions: Vec<Direction>, graph: HashMap<String, Node>) -> u64 {
+fn process(
+    directions: Vec<Direction>,
+    graph: HashMap<String, Node>,
+    start_suffix: char,
+    target_suffix: char,
+    progress_interval: u64,
+) -> u64 {
     // start at every node that ends with A
     let start_nodes: Vec<&Node> = graph
         .values()
-        .filter(|node| node.label.ends_with('A'))
+        .filter(|node| node.label.ends_with(start_suffix))
         .collect();
 
     // for each A node, figure out which cycles will lead them to repeat Z
@@ -119,7 +212,7 @@ fn process(directions: Vec<Direction>, graph: HashMap<String, Node>) -> u64 {
                 Direction::Right => graph.get(&cur_node.right).unwrap(),
             };
             cur_node = temp;
-            if cur_node.label.ends_with('Z') {
+            if cur_node.label.ends_with(target_suffix) {
                 if zs.contains(&cur_node) {
                     let k = ks[zs.iter().position(|z| z == &cur_node).unwrap()];
                     cycles.push(ZCycle {
@@ -158,10 +251,9 @@ fn process(directions: Vec<Direction>, graph: HashMap<String, Node>) -> u64 {
     // here's the fun part. we know that the solution is, for each cycle, k + rl where k is the cycle's constant, l is the cycle's length,
     // and r is the number of times that cycle has been repeated. For any value s, we can quickly check if s satisfies at least one z cycle per starting node
     // So, we continually try s by taking our biggest cycle (or combination of cycles for a single node) and repeating it over and over and checking each time
-    let mut found_solution = false;
-    let mut num_cycle_repeats = 0;
-    while !found_solution {
-        if num_cycle_repeats % 10000000 == 0 {
+    let mut num_cycle_repeats: u64 = 0;
+    loop {
+        if num_cycle_repeats.is_multiple_of(progress_interval) {
             println!("trying with {} cycle repeats", num_cycle_repeats);
         }
         'cycle_loop: for cycle in best_cycle_list {
@@ -174,22 +266,15 @@ fn process(directions: Vec<Direction>, graph: HashMap<String, Node>) -> u64 {
                 }
                 continue 'cycle_loop;
             }
-            found_solution = true;
             return steps_attempt;
         }
         num_cycle_repeats += 1;
     }
-
-    unreachable!("we either find a solution or loop forever.");
 }
 
 // given a cycle list, return the average number of steps it will skip per iteration
-fn get_power(cycle_list: &Vec<ZCycle>) -> u64 {
-    cycle_list
-        .iter()
-        .map(|cycle| cycle.length)
-        .fold(0, |acc, l| acc + l)
-        / (cycle_list.len() as u64)
+fn get_power(cycle_list: &[ZCycle]) -> u64 {
+    cycle_list.iter().map(|cycle| cycle.length).sum::<u64>() / (cycle_list.len() as u64)
 }
 
 struct ZCycle {
@@ -202,7 +287,7 @@ struct ZCycle {
 impl ZCycle {
     // after a given number of steps, determine whether this cycle will land on Z
     fn is_valid_steps(&self, steps: u64) -> bool {
-        (steps - self.constant) % self.length == 0
+        (steps - self.constant).is_multiple_of(self.length)
     }
 }
 
@@ -217,3 +302,26 @@ struct Node {
     left: String,
     right: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)
+";
+
+    #[test]
+    fn example() {
+        assert_eq!(solve(EXAMPLE.to_string(), 'A', 'Z', 1_000_000).unwrap(), 6);
+    }
+}
@@ -3,7 +3,7 @@ use aoc9lvl1::Config;
 use std::process;
 fn main() {
     // remember to change the module name!
-    if let Err(e) = aoc9lvl1::run(Config::make()) {
+    if let Err(e) = Config::make().and_then(aoc9lvl1::run) {
         eprintln!("{}", e);
         process::exit(1);
     } else {
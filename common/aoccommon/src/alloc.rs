@@ -0,0 +1,216 @@
+//! heap usage reporting through a counting global allocator
+//!
+//! the allocator is only installed when the `alloc-stats` feature is enabled,
+//! otherwise `measure` just runs the closure it's given
+//!
+//! the counters are kept per thread, so a measurement only sees the allocations made by the
+//! thread it runs on. that keeps the inputs of a parallel batch run (each solved on a thread
+//! of its own) from mixing their numbers together.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt;
+
+// const initialized cells without destructors, so touching them never allocates
+thread_local! {
+    // bytes currently allocated by this thread, less what it has freed (which can include
+    // memory allocated elsewhere, hence signed)
+    static CURRENT: Cell<isize> = const { Cell::new(0) };
+    // highest value CURRENT has reached since the last reset
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+    // number of allocation calls made (reallocs count as one)
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    // total bytes requested across all allocation calls
+    static BYTES_ALLOCATED: Cell<usize> = const { Cell::new(0) };
+    // set while a batch run collects the stats itself, so measure doesn't print them
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// a wrapper around the system allocator that keeps running totals
+pub struct CountingAllocator;
+
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+impl CountingAllocator {
+    // try_with since the allocator is still called while a thread's locals are torn down
+    fn record_alloc(size: usize) {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        let _ = BYTES_ALLOCATED.try_with(|n| n.set(n.get() + size));
+        if let Ok(current) = CURRENT.try_with(|c| {
+            c.set(c.get() + size as isize);
+            c.get()
+        }) {
+            let _ = PEAK.try_with(|p| p.set(p.get().max(current)));
+        }
+    }
+
+    fn record_dealloc(size: usize) {
+        let _ = CURRENT.try_with(|c| c.set(c.get() - size as isize));
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::record_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            // treat a realloc as freeing the old block and allocating the new one
+            Self::record_dealloc(layout.size());
+            Self::record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// heap usage over some stretch of the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocStats {
+    /// most bytes live on the heap at any one time
+    pub peak_bytes: usize,
+    /// number of allocation calls
+    pub allocations: usize,
+    /// total bytes requested by those calls
+    pub bytes_allocated: usize,
+}
+
+impl fmt::Display for AllocStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "peak {} bytes, {} allocations, {} bytes allocated",
+            self.peak_bytes, self.allocations, self.bytes_allocated
+        )
+    }
+}
+
+/// run a closure and return its result along with the heap usage it caused on this thread
+pub fn track<T>(f: impl FnOnce() -> T) -> (T, AllocStats) {
+    // start the peak over from whatever is live right now, remembering the outer one so a
+    // measurement inside another doesn't hide the outer peak
+    let outer_peak = PEAK.get();
+    let start = CURRENT.get();
+    PEAK.set(start);
+    let allocations_before = ALLOCATIONS.get();
+    let bytes_before = BYTES_ALLOCATED.get();
+
+    let result = f();
+
+    let peak = PEAK.get();
+    PEAK.set(peak.max(outer_peak));
+    let stats = AllocStats {
+        peak_bytes: (peak - start).max(0) as usize,
+        allocations: ALLOCATIONS.get() - allocations_before,
+        bytes_allocated: BYTES_ALLOCATED.get() - bytes_before,
+    };
+    (result, stats)
+}
+
+/// run a closure and, if alloc stats are enabled, print its heap usage to stderr under the given label
+pub fn measure<T>(label: &str, f: impl FnOnce() -> T) -> T {
+    if cfg!(feature = "alloc-stats") && !QUIET.get() {
+        let (result, stats) = track(f);
+        eprintln!("{}: {}", label, stats);
+        result
+    } else {
+        f()
+    }
+}
+
+/// the heap usage of a whole closure, with any `measure` inside it kept quiet
+///
+/// None if alloc stats aren't enabled. used by batch runs, which put each input's usage in
+/// the report instead of printing unlabelled lines for every one of them
+pub fn track_quietly<T>(f: impl FnOnce() -> T) -> (T, Option<AllocStats>) {
+    if !cfg!(feature = "alloc-stats") {
+        return (f(), None);
+    }
+    let was_quiet = QUIET.replace(true);
+    let (result, stats) = track(f);
+    QUIET.set(was_quiet);
+    (result, Some(stats))
+}
+
+#[cfg(all(test, feature = "alloc-stats"))]
+mod tests {
+    use super::*;
+    use std::hint::black_box;
+    use std::thread;
+
+    #[test]
+    fn counts_what_the_closure_allocates() {
+        let (buffer, stats) = track(|| black_box(vec![0u8; 10_000]));
+        assert_eq!(stats.allocations, 1);
+        assert_eq!(stats.bytes_allocated, 10_000);
+        assert_eq!(stats.peak_bytes, 10_000);
+        drop(buffer);
+
+        // freed memory still counts toward the peak and the total
+        let ((), stats) = track(|| {
+            for _ in 0..5 {
+                black_box(vec![0u8; 1000]);
+            }
+        });
+        assert_eq!(stats.allocations, 5);
+        assert_eq!(stats.bytes_allocated, 5000);
+        assert_eq!(stats.peak_bytes, 1000);
+    }
+
+    #[test]
+    fn other_threads_dont_count() {
+        let ((), stats) = track(|| {
+            thread::scope(|scope| {
+                // busy allocating on another thread the whole time
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        black_box(vec![0u8; 100_000]);
+                    }
+                });
+            });
+        });
+        assert!(stats.bytes_allocated < 100_000, "{}", stats);
+    }
+
+    #[test]
+    fn nested_measurements_keep_the_outer_peak() {
+        let ((), outer) = track(|| {
+            let big = black_box(vec![0u8; 50_000]);
+            drop(big);
+            let ((), inner) = track(|| {
+                black_box(vec![0u8; 100]);
+            });
+            assert_eq!(inner.peak_bytes, 100);
+        });
+        assert_eq!(outer.peak_bytes, 50_000);
+        assert_eq!(outer.allocations, 2);
+    }
+
+    #[test]
+    fn quiet_tracking_reports_the_whole_closure() {
+        let (_, stats) = track_quietly(|| measure("inner", || black_box(vec![0u8; 300])));
+        let stats = stats.unwrap();
+        assert_eq!(stats.bytes_allocated, 300);
+        assert!(!QUIET.get());
+    }
+}
@@ -0,0 +1,348 @@
+//! run a solver over every file in a directory in parallel
+
+use std::fmt;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+
+use crate::alloc::{self, AllocStats};
+
+/// what happened when we ran the solver on one file
+#[derive(Debug)]
+pub struct FileResult {
+    pub path: PathBuf,
+    pub elapsed: Duration,
+    /// the answer, or a description of what went wrong
+    pub outcome: Result<String, String>,
+    /// heap usage of the solver, when built with alloc stats and it got to finish
+    pub alloc: Option<AllocStats>,
+}
+
+/// results for every file in a batch, sorted by path
+#[derive(Debug)]
+pub struct BatchReport {
+    pub results: Vec<FileResult>,
+}
+
+impl BatchReport {
+    pub fn failures(&self) -> usize {
+        self.results.iter().filter(|r| r.outcome.is_err()).count()
+    }
+
+    /// sum of the time spent on each file (more than wall time since they run in parallel)
+    pub fn total_time(&self) -> Duration {
+        self.results.iter().map(|r| r.elapsed).sum()
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            match &result.outcome {
+                Ok(answer) => write!(f, "{}: {}", file_name(&result.path), answer)?,
+                Err(e) => write!(f, "{}: error: {}", file_name(&result.path), e)?,
+            }
+            match &result.alloc {
+                Some(stats) => writeln!(f, " ({:.2?}, {})", result.elapsed, stats)?,
+                None => writeln!(f, " ({:.2?})", result.elapsed)?,
+            }
+        }
+
+        let slowest = self.results.iter().max_by_key(|r| r.elapsed);
+        write!(
+            f,
+            "{} inputs: {} ok, {} failed, {:.2?} total",
+            self.results.len(),
+            self.results.len() - self.failures(),
+            self.failures(),
+            self.total_time()
+        )?;
+        if let Some(slowest) = slowest {
+            write!(
+                f,
+                ", slowest {} ({:.2?})",
+                file_name(&slowest.path),
+                slowest.elapsed
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// solve every file in dir, spreading them over all available cores
+///
+/// a file that fails to read, returns an error, panics or takes longer than timeout is
+/// recorded in the report and doesn't stop the rest of the batch. each file is solved on a
+/// thread of its own so one that times out can be left to finish in the background (rust
+/// has no way to stop a thread), which is why the solver has to own everything it uses.
+///
+/// the panic hook is left alone, so a panicking solver still gets its message printed by
+/// whatever hook the program has, as well as being recorded in the report
+pub fn run_batch<T, F>(
+    dir: &Path,
+    timeout: Option<Duration>,
+    solve: F,
+) -> anyhow::Result<BatchReport>
+where
+    T: fmt::Display,
+    F: Fn(String) -> anyhow::Result<T> + Send + Sync + 'static,
+{
+    let mut paths = fs::read_dir(dir)
+        .map_err(|e| anyhow!("failed to read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(paths.len());
+    // each worker takes the next unclaimed file until there are none left
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(paths.len()));
+    let solve = Arc::new(solve);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Some(path) = paths.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let result = solve_file(path, timeout, &solve);
+                    results.lock().unwrap().push(result);
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(BatchReport { results })
+}
+
+fn solve_file<T, F>(path: &Path, timeout: Option<Duration>, solve: &Arc<F>) -> FileResult
+where
+    T: fmt::Display,
+    F: Fn(String) -> anyhow::Result<T> + Send + Sync + 'static,
+{
+    let start = Instant::now();
+    let (outcome, alloc) = match fs::read_to_string(path) {
+        Ok(input) => {
+            let (sender, receiver) = mpsc::channel();
+            let solve = Arc::clone(solve);
+            thread::spawn(move || {
+                // the thread is ours alone, so its allocations are this input's
+                let result = alloc::track_quietly(|| {
+                    match panic::catch_unwind(AssertUnwindSafe(|| solve(input))) {
+                        Ok(Ok(answer)) => Ok(answer.to_string()),
+                        Ok(Err(e)) => Err(e.to_string()),
+                        Err(payload) => Err(panic_message(payload)),
+                    }
+                });
+                // nobody is listening any more if we timed out
+                let _ = sender.send(result);
+            });
+            match timeout {
+                Some(timeout) => receiver
+                    .recv_timeout(timeout)
+                    .unwrap_or_else(|_| (Err(format!("timed out after {:.2?}", timeout)), None)),
+                // the thread always sends unless it was killed outright
+                None => receiver
+                    .recv()
+                    .unwrap_or_else(|_| (Err("solver thread died".to_string()), None)),
+            }
+        }
+        Err(e) => (Err(format!("failed to read file: {}", e)), None),
+    };
+
+    FileResult {
+        path: path.to_path_buf(),
+        elapsed: start.elapsed(),
+        outcome,
+        alloc,
+    }
+}
+
+// the part of a path worth printing in a report
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+// pull the message out of a panic so it can go in the report
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        format!("panicked: {}", s)
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        format!("panicked: {}", s)
+    } else {
+        "panicked".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a fresh directory holding the given files
+    fn dir_with(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aoc-batch-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn slow_inputs_time_out_without_holding_up_the_rest() {
+        let dir = dir_with("timeout", &[("fast.txt", "0"), ("slow.txt", "600")]);
+        let start = Instant::now();
+        let report = run_batch(&dir, Some(Duration::from_millis(200)), |input| {
+            let secs = input.parse::<u64>()?;
+            thread::sleep(Duration::from_millis(secs * 1000));
+            Ok(secs)
+        })
+        .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_eq!(report.results[0].outcome, Ok("0".to_string()));
+        let slow = report.results[1].outcome.as_ref().unwrap_err();
+        assert!(slow.starts_with("timed out after"), "{}", slow);
+    }
+
+    #[test]
+    fn panics_are_recorded_and_the_batch_goes_on() {
+        let dir = dir_with(
+            "panic",
+            &[("a.txt", "1"), ("b.txt", "boom"), ("c.txt", "3")],
+        );
+        let report = run_batch(&dir, None, |input| {
+            if input == "boom" {
+                panic!("kaboom");
+            }
+            Ok(input)
+        })
+        .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let outcomes = report
+            .results
+            .iter()
+            .map(|r| r.outcome.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            [
+                Ok("1".to_string()),
+                Err("panicked: kaboom".to_string()),
+                Ok("3".to_string())
+            ]
+        );
+        assert_eq!(report.failures(), 1);
+    }
+
+    #[test]
+    fn results_are_sorted_by_path() {
+        let names = ["09.txt", "01.txt", "10.txt", "02.txt", "05.txt", "03.txt"];
+        let files = names.map(|name| (name, name));
+        let dir = dir_with("sorted", &files);
+        let report = run_batch(&dir, None, |input| {
+            // the later files finish first
+            let day = input[..2].parse::<u64>()?;
+            thread::sleep(Duration::from_millis(50 - 4 * day));
+            Ok(input)
+        })
+        .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut sorted = names.to_vec();
+        sorted.sort();
+        let answers = report
+            .results
+            .iter()
+            .map(|r| r.outcome.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(answers, sorted);
+        assert!(report.results.is_sorted_by(|a, b| a.path <= b.path));
+    }
+
+    #[test]
+    fn unreadable_files_are_failures() {
+        // not utf-8, so read_to_string fails whoever is running the tests
+        let dir = dir_with("unreadable", &[("ok.txt", "fine")]);
+        fs::write(dir.join("bad.txt"), [0xff, 0xfe, 0x00]).unwrap();
+        let report = run_batch(&dir, None, Ok).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let bad = report.results[0].outcome.as_ref().unwrap_err();
+        assert!(bad.starts_with("failed to read file: "), "{}", bad);
+        assert_eq!(report.results[1].outcome, Ok("fine".to_string()));
+        assert!(report.to_string().contains("2 inputs: 1 ok, 1 failed"));
+    }
+
+    #[test]
+    fn an_empty_directory_is_an_empty_report() {
+        let dir = dir_with("empty", &[]);
+        let report = run_batch(&dir, None, Ok::<String, anyhow::Error>).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(report.results.is_empty());
+        assert_eq!(report.failures(), 0);
+        assert!(report.to_string().starts_with("0 inputs: 0 ok, 0 failed"));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc-stats")]
+    fn each_input_gets_its_own_alloc_stats() {
+        let dir = dir_with("alloc", &[("big.txt", "300000"), ("small.txt", "100000")]);
+        let report = run_batch(&dir, None, |input| {
+            let buffer = std::hint::black_box(vec![0u8; input.parse::<usize>()?]);
+            Ok(buffer.len())
+        })
+        .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // the solvers ran side by side, but each only sees its own buffer (plus the answer)
+        for (result, size) in report.results.iter().zip([300_000, 100_000]) {
+            let stats = result.alloc.unwrap();
+            assert!(
+                (size..size + 1000).contains(&stats.peak_bytes),
+                "{:?}",
+                stats
+            );
+            assert!(
+                (size..size + 1000).contains(&stats.bytes_allocated),
+                "{:?}",
+                stats
+            );
+        }
+        assert!(report.to_string().contains("peak 3000"));
+    }
+
+    #[test]
+    #[cfg(not(feature = "alloc-stats"))]
+    fn no_alloc_stats_without_the_feature() {
+        let dir = dir_with("no-alloc", &[("a.txt", "1")]);
+        let report = run_batch(&dir, None, Ok::<String, anyhow::Error>).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.results[0].alloc, None);
+        assert!(!report.to_string().contains("peak"));
+    }
+
+    #[test]
+    fn a_missing_directory_is_an_error() {
+        let dir = std::env::temp_dir().join("aoc-batch-does-not-exist");
+        assert!(run_batch(&dir, None, Ok::<String, anyhow::Error>).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+//! settings shared by every day, read from `aoc.toml` files
+//!
+//! the project file is the first `aoc.toml` found walking up from the current directory.
+//! a user file at `$XDG_CONFIG_HOME/aoc/aoc.toml` (or `~/.config/aoc/aoc.toml`) is layered
+//! on top of it, so anything set there wins. command line flags win over both.
+//!
+//! ```toml
+//! input_dir = "inputs"             # day N reads <input_dir>/NN.txt when --input isn't given
+//! session_token = "~/.aoc_session" # file holding the adventofcode.com session cookie
+//! output_format = "text"           # text, json or csv
+//! timeout_secs = 60                # give up on an input in a batch run (--inputs) after this
+//! answers = "answers.toml"         # registry of known good answers
+//!
+//! [day.2]
+//! limits = { red = 12, green = 13, blue = 14 }
+//!
+//! [day.8]
+//! progress_interval = 10000000
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// name of the config file we look for
+pub const FILE_NAME: &str = "aoc.toml";
+
+// keys whose values are paths, resolved relative to the file that sets them
+const PATH_KEYS: [&str; 3] = ["input_dir", "session_token", "answers"];
+
+/// how results should be printed when a day supports more than one format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!(
+                "unknown output format {:?}, expected text, json or csv",
+                s
+            )),
+        }
+    }
+}
+
+/// everything that can be set in an `aoc.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AocConfig {
+    /// directory holding puzzle inputs named by day (01.txt, 02.txt, ...)
+    pub input_dir: Option<PathBuf>,
+    /// file containing the adventofcode.com session token
+    pub session_token: Option<PathBuf>,
+    pub output_format: OutputFormat,
+    /// how long a single input in a batch run may take before it's given up on
+    pub timeout_secs: Option<u64>,
+    /// file recording known answers per day and part
+    pub answers: Option<PathBuf>,
+    /// per day parameters, keyed by day number
+    day: HashMap<String, toml::Table>,
+}
+
+impl AocConfig {
+    /// load the project file and user overrides, falling back to defaults if neither exists
+    pub fn load() -> anyhow::Result<Self> {
+        let project = env::current_dir()
+            .ok()
+            .and_then(|dir| find_project_file(&dir));
+        let user = user_file().filter(|path| path.is_file());
+        AocConfig::load_files(project.as_deref(), user.as_deref())
+    }
+
+    /// the project file with the user file on top, either of which may be missing
+    fn load_files(project: Option<&Path>, user: Option<&Path>) -> anyhow::Result<Self> {
+        let mut merged = toml::Table::new();
+        for path in [project, user].into_iter().flatten() {
+            merge(&mut merged, read_table(path)?);
+        }
+
+        AocConfig::deserialize(merged).map_err(|e| anyhow!("invalid aoc.toml: {}", e))
+    }
+
+    /// the input file to use for a day when none was given on the command line
+    pub fn input_path(&self, day: u32) -> anyhow::Result<Option<PathBuf>> {
+        if let Some(input) = self.day_param::<PathBuf>(day, "input")? {
+            return Ok(Some(input));
+        }
+        Ok(self
+            .input_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{:02}.txt", day))))
+    }
+
+    /// how long each input in a batch run gets, if there's a limit
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_secs.map(Duration::from_secs)
+    }
+
+    /// look up a parameter in a day's table, returning None if it isn't set
+    pub fn day_param<T: DeserializeOwned>(&self, day: u32, key: &str) -> anyhow::Result<Option<T>> {
+        match self.day_table(day).and_then(|table| table.get(key)) {
+            Some(value) => value
+                .clone()
+                .try_into()
+                .map(Some)
+                .map_err(|e| anyhow!("invalid value for {} in [day.{}]: {}", key, day, e)),
+            None => Ok(None),
+        }
+    }
+
+    fn day_table(&self, day: u32) -> Option<&toml::Table> {
+        self.day.get(&day.to_string())
+    }
+}
+
+/// walk up from a directory looking for a project config file
+fn find_project_file(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// where the user level config lives
+fn user_file() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("aoc").join(FILE_NAME))
+}
+
+/// read a config file and make its paths relative to where it lives
+fn read_table(path: &Path) -> anyhow::Result<toml::Table> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read {}: {}", path.display(), e))?;
+    let mut table = contents
+        .parse::<toml::Table>()
+        .map_err(|e| anyhow!("failed to parse {}: {}", path.display(), e))?;
+
+    let base = path.parent().unwrap_or(Path::new("."));
+    for key in PATH_KEYS {
+        if let Some(toml::Value::String(value)) = table.get_mut(key) {
+            *value = resolve_path(base, value).to_string_lossy().into_owned();
+        }
+    }
+    if let Some(toml::Value::Table(days)) = table.get_mut("day") {
+        for (_, day) in days.iter_mut() {
+            if let Some(toml::Value::String(value)) = day.get_mut("input") {
+                *value = resolve_path(base, value).to_string_lossy().into_owned();
+            }
+        }
+    }
+
+    Ok(table)
+}
+
+/// expand a leading ~ and anchor relative paths at base
+fn resolve_path(base: &Path, value: &str) -> PathBuf {
+    if let Some(rest) = value.strip_prefix("~/") {
+        if let Some(home) = env::var_os("HOME") {
+            return PathBuf::from(home).join(rest);
+        }
+    }
+    base.join(value)
+}
+
+/// recursively lay the override table on top of the base table
+fn merge(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(override_table)) => {
+                merge(base_table, override_table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(toml: &str) -> toml::Table {
+        toml.parse().unwrap()
+    }
+
+    fn config(toml: &str) -> AocConfig {
+        AocConfig::deserialize(table(toml)).unwrap()
+    }
+
+    #[test]
+    fn user_file_wins_over_project_file() {
+        let mut merged = table(
+            r#"
+            output_format = "csv"
+            timeout_secs = 10
+            [day.2]
+            limits = { red = 1, green = 2 }
+            [day.8]
+            progress_interval = 5
+            "#,
+        );
+        merge(
+            &mut merged,
+            table(
+                r#"
+                timeout_secs = 20
+                [day.2]
+                limits = { red = 7 }
+                "#,
+            ),
+        );
+        let cfg = AocConfig::deserialize(merged).unwrap();
+
+        assert_eq!(cfg.output_format, OutputFormat::Csv);
+        assert_eq!(cfg.timeout(), Some(Duration::from_secs(20)));
+        // tables merge key by key, so the user's red doesn't wipe out the project's green
+        let limits = cfg
+            .day_param::<HashMap<String, u32>>(2, "limits")
+            .unwrap()
+            .unwrap();
+        assert_eq!(limits["red"], 7);
+        assert_eq!(limits["green"], 2);
+        assert_eq!(
+            cfg.day_param::<u64>(8, "progress_interval").unwrap(),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn paths_resolve_from_the_file_or_home() {
+        let base = Path::new("/projects/aoc");
+        assert_eq!(
+            resolve_path(base, "inputs"),
+            PathBuf::from("/projects/aoc/inputs")
+        );
+        assert_eq!(
+            resolve_path(base, "/abs/inputs"),
+            PathBuf::from("/abs/inputs")
+        );
+        if let Some(home) = env::var_os("HOME") {
+            assert_eq!(
+                resolve_path(base, "~/inputs"),
+                PathBuf::from(home).join("inputs")
+            );
+        }
+    }
+
+    #[test]
+    fn paths_in_a_file_are_relative_to_it() {
+        let dir = env::temp_dir().join(format!("aoc-config-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(FILE_NAME);
+        fs::write(
+            &path,
+            "input_dir = \"inputs\"\n[day.3]\ninput = \"special/03.txt\"\n",
+        )
+        .unwrap();
+        let cfg = AocConfig::deserialize(read_table(&path).unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            cfg.input_path(1).unwrap(),
+            Some(dir.join("inputs").join("01.txt"))
+        );
+        assert_eq!(cfg.input_path(3).unwrap(), Some(dir.join("special/03.txt")));
+    }
+
+    #[test]
+    fn day_params_of_the_wrong_type_are_errors() {
+        let cfg = config("[day.8]\nprogress_interval = \"often\"\n");
+        let err = cfg.day_param::<u64>(8, "progress_interval").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid value for progress_interval in [day.8]: "));
+        assert_eq!(cfg.day_param::<u64>(8, "start").unwrap(), None);
+        assert_eq!(cfg.day_param::<u64>(9, "progress_interval").unwrap(), None);
+    }
+
+    #[test]
+    fn an_input_that_isnt_a_path_is_an_error() {
+        let cfg = config("input_dir = \"inputs\"\n[day.4]\ninput = 4\n");
+        let err = cfg.input_path(4).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid value for input in [day.4]: "));
+        assert_eq!(
+            cfg.input_path(5).unwrap(),
+            Some(PathBuf::from("inputs/05.txt"))
+        );
+    }
+
+    #[test]
+    fn session_and_answer_files_resolve_from_the_file_setting_them() {
+        let root = env::temp_dir().join(format!("aoc-config-files-{}", std::process::id()));
+        let (project, user) = (root.join("project"), root.join("user"));
+        fs::create_dir_all(&project).unwrap();
+        fs::create_dir_all(&user).unwrap();
+        fs::write(
+            project.join(FILE_NAME),
+            "session_token = \".aoc_session\"\nanswers = \"answers.toml\"\n",
+        )
+        .unwrap();
+        fs::write(user.join(FILE_NAME), "answers = \"mine/answers.toml\"\n").unwrap();
+
+        let project_only = AocConfig::load_files(Some(&project.join(FILE_NAME)), None).unwrap();
+        let both =
+            AocConfig::load_files(Some(&project.join(FILE_NAME)), Some(&user.join(FILE_NAME)))
+                .unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            project_only.session_token,
+            Some(project.join(".aoc_session"))
+        );
+        assert_eq!(project_only.answers, Some(project.join("answers.toml")));
+        // the user file's answers win, the project's session token is still there
+        assert_eq!(both.session_token, Some(project.join(".aoc_session")));
+        assert_eq!(both.answers, Some(user.join("mine/answers.toml")));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(AocConfig::deserialize(table("session_file = \"x\"")).is_err());
+    }
+}
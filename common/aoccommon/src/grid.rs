@@ -0,0 +1,219 @@
+//! a 2d grid of cells for the map and schematic style puzzles
+//!
+//! rows don't have to be the same length, a position is only in bounds if its row
+//! actually has a cell at that column
+
+/// (row, column), both 0 based
+pub type Pos = (usize, usize);
+
+/// offsets to the cells sharing an edge
+pub const ORTHOGONAL: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+/// offsets to the cells sharing an edge or a corner
+pub const ALL_AROUND: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    rows: Vec<Vec<T>>,
+}
+
+/// a horizontal stretch of neighbouring cells in one row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Run {
+    pub row: usize,
+    pub start: usize,
+    /// exclusive
+    pub end: usize,
+}
+
+impl Run {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// every position the run covers
+    pub fn cells(&self) -> impl Iterator<Item = Pos> {
+        let row = self.row;
+        (self.start..self.end).map(move |col| (row, col))
+    }
+}
+
+impl Grid<char> {
+    /// one row per line, one cell per character
+    pub fn parse(input: &str) -> Self {
+        Grid {
+            rows: input.lines().map(|line| line.chars().collect()).collect(),
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Self {
+        Grid { rows }
+    }
+
+    /// number of rows
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// length of a row, 0 if the row doesn't exist
+    pub fn row_len(&self, row: usize) -> usize {
+        self.rows.get(row).map_or(0, |r| r.len())
+    }
+
+    pub fn row(&self, row: usize) -> Option<&[T]> {
+        self.rows.get(row).map(|r| r.as_slice())
+    }
+
+    pub fn get(&self, (row, col): Pos) -> Option<&T> {
+        self.rows.get(row).and_then(|r| r.get(col))
+    }
+
+    pub fn get_mut(&mut self, (row, col): Pos) -> Option<&mut T> {
+        self.rows.get_mut(row).and_then(|r| r.get_mut(col))
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        self.get(pos).is_some()
+    }
+
+    /// every cell along with its position, row by row
+    pub fn cells(&self) -> impl Iterator<Item = (Pos, &T)> {
+        self.rows.iter().enumerate().flat_map(|(row, cells)| {
+            cells
+                .iter()
+                .enumerate()
+                .map(move |(col, cell)| ((row, col), cell))
+        })
+    }
+
+    /// in bounds positions reached by applying each offset to pos
+    pub fn neighbors<'g>(
+        &'g self,
+        pos: Pos,
+        offsets: &'g [(isize, isize)],
+    ) -> impl Iterator<Item = Pos> + 'g {
+        offsets.iter().filter_map(move |&(d_row, d_col)| {
+            let row = pos.0.checked_add_signed(d_row)?;
+            let col = pos.1.checked_add_signed(d_col)?;
+            self.contains((row, col)).then_some((row, col))
+        })
+    }
+
+    /// the up to 4 positions sharing an edge with pos
+    pub fn neighbors4(&self, pos: Pos) -> impl Iterator<Item = Pos> + '_ {
+        self.neighbors(pos, &ORTHOGONAL)
+    }
+
+    /// the up to 8 positions sharing an edge or corner with pos
+    pub fn neighbors8(&self, pos: Pos) -> impl Iterator<Item = Pos> + '_ {
+        self.neighbors(pos, &ALL_AROUND)
+    }
+
+    /// maximal horizontal runs of cells matching a predicate, like the digits of a number
+    pub fn runs(&self, matches: impl Fn(&T) -> bool) -> Vec<Run> {
+        let mut runs = Vec::new();
+        for (row, cells) in self.rows.iter().enumerate() {
+            let mut start = None;
+            for (col, cell) in cells.iter().enumerate() {
+                match (matches(cell), start) {
+                    (true, None) => start = Some(col),
+                    (false, Some(s)) => {
+                        runs.push(Run {
+                            row,
+                            start: s,
+                            end: col,
+                        });
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(s) = start {
+                runs.push(Run {
+                    row,
+                    start: s,
+                    end: cells.len(),
+                });
+            }
+        }
+        runs
+    }
+
+    /// the cells a run covers
+    pub fn run_cells(&self, run: &Run) -> &[T] {
+        &self.rows[run.row][run.start..run.end]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(positions: impl Iterator<Item = Pos>) -> Vec<Pos> {
+        let mut positions = positions.collect::<Vec<_>>();
+        positions.sort();
+        positions
+    }
+
+    #[test]
+    fn neighbors_stay_inside_ragged_rows() {
+        // the middle row is longer than the ones around it
+        let grid = Grid::parse("ab\nabcd\na");
+        assert_eq!(sorted(grid.neighbors8((1, 3))), [(1, 2)]);
+        assert_eq!(
+            sorted(grid.neighbors8((1, 1))),
+            [(0, 0), (0, 1), (1, 0), (1, 2), (2, 0)]
+        );
+        assert_eq!(sorted(grid.neighbors4((0, 1))), [(0, 0), (1, 1)]);
+        // a position off the end of its row still only gets real neighbours
+        assert_eq!(sorted(grid.neighbors4((2, 2))), [(1, 2)]);
+    }
+
+    #[test]
+    fn corners_have_fewer_neighbors() {
+        let grid = Grid::parse("abc\ndef\nghi");
+        assert_eq!(sorted(grid.neighbors4((0, 0))), [(0, 1), (1, 0)]);
+        assert_eq!(sorted(grid.neighbors8((0, 0))), [(0, 1), (1, 0), (1, 1)]);
+        assert_eq!(sorted(grid.neighbors4((2, 2))), [(1, 2), (2, 1)]);
+        assert_eq!(sorted(grid.neighbors8((2, 2))), [(1, 1), (1, 2), (2, 1)]);
+        assert_eq!(sorted(grid.neighbors8((0, 2))), [(0, 1), (1, 1), (1, 2)]);
+        assert_eq!(grid.neighbors8((1, 1)).count(), 8);
+    }
+
+    #[test]
+    fn runs_end_at_the_end_of_their_row() {
+        let grid = Grid::parse("..12\n34..\n5\n\n67");
+        let runs = grid.runs(|c| c.is_ascii_digit());
+        let run = |row, start, end| Run { row, start, end };
+        assert_eq!(
+            runs,
+            [run(0, 2, 4), run(1, 0, 2), run(2, 0, 1), run(4, 0, 2)]
+        );
+        assert_eq!(grid.run_cells(&runs[0]), ['1', '2']);
+        assert_eq!(runs[3].cells().collect::<Vec<_>>(), [(4, 0), (4, 1)]);
+    }
+
+    #[test]
+    fn out_of_bounds_is_none() {
+        let grid = Grid::parse("ab\nc");
+        assert_eq!(grid.get((1, 1)), None);
+        assert_eq!(grid.get((2, 0)), None);
+        assert_eq!(grid.row_len(5), 0);
+        assert!(grid.contains((0, 1)));
+    }
+}
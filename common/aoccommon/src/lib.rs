@@ -0,0 +1,9 @@
+//! shared helpers for the individual day crates
+
+pub mod alloc;
+pub mod batch;
+pub mod config;
+pub mod grid;
+pub mod parsing;
+pub mod report;
+pub mod schematic;
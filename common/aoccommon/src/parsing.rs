@@ -0,0 +1,345 @@
+//! small combinators for the input shapes that keep coming up
+//!
+//! everything works on `Span`s, slices of the input that remember which line and column
+//! they started at, so a failure can say exactly where the input went wrong
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// a parse failure and where in the input it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1 based line number
+    pub line: usize,
+    /// 1 based column, counted in bytes
+    pub col: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.col, self.msg)
+    }
+}
+
+impl Error for ParseError {}
+
+pub type Result<T> = std::result::Result<T, ParseError>;
+
+/// a piece of the input along with where it starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span<'a> {
+    text: &'a str,
+    line: usize,
+    col: usize,
+}
+
+impl<'a> Span<'a> {
+    /// a span starting at the given 1 based line and column
+    pub fn new(text: &'a str, line: usize, col: usize) -> Self {
+        Span { text, line, col }
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// an error pointing at the start of this span
+    pub fn error(&self, msg: impl Into<String>) -> ParseError {
+        ParseError {
+            line: self.line,
+            col: self.col,
+            msg: msg.into(),
+        }
+    }
+
+    /// the part of this span between byte offsets start and end
+    pub fn slice(&self, start: usize, end: usize) -> Span<'a> {
+        Span {
+            text: &self.text[start..end],
+            line: self.line,
+            col: self.col + start,
+        }
+    }
+
+    /// drop leading and trailing whitespace
+    pub fn trim(&self) -> Span<'a> {
+        let start = self.text.len() - self.text.trim_start().len();
+        let end = self.text.trim_end().len().max(start);
+        self.slice(start, end)
+    }
+
+    /// strip a prefix (ignoring leading whitespace), failing if it isn't there
+    pub fn expect(&self, prefix: &str) -> Result<Span<'a>> {
+        let trimmed = self.trim_start();
+        if trimmed.text.starts_with(prefix) {
+            Ok(trimmed.slice(prefix.len(), trimmed.text.len()))
+        } else {
+            Err(trimmed.error(format!("expected {:?}, found {:?}", prefix, trimmed.text)))
+        }
+    }
+
+    /// strip a suffix (ignoring trailing whitespace), failing if it isn't there
+    pub fn expect_suffix(&self, suffix: &str) -> Result<Span<'a>> {
+        let trimmed = self.slice(0, self.text.trim_end().len());
+        if trimmed.text.ends_with(suffix) {
+            Ok(trimmed.slice(0, trimmed.text.len() - suffix.len()))
+        } else {
+            Err(trimmed.error(format!(
+                "expected {:?} at the end of {:?}",
+                suffix, trimmed.text
+            )))
+        }
+    }
+
+    /// split around the first occurrence of sep, failing if there isn't one
+    pub fn split_once(&self, sep: &str) -> Result<(Span<'a>, Span<'a>)> {
+        match self.text.find(sep) {
+            Some(i) => Ok((self.slice(0, i), self.slice(i + sep.len(), self.text.len()))),
+            None => Err(self.error(format!("expected {:?} in {:?}", sep, self.text))),
+        }
+    }
+
+    /// split on every occurrence of sep, trimming each piece
+    pub fn list(&self, sep: &'a str) -> impl Iterator<Item = Span<'a>> + 'a {
+        let span = *self;
+        self.text.split(sep).scan(0, move |offset, piece| {
+            let start = *offset;
+            *offset += piece.len() + sep.len();
+            Some(span.slice(start, start + piece.len()).trim())
+        })
+    }
+
+    /// split on runs of whitespace
+    pub fn words(&self) -> impl Iterator<Item = Span<'a>> + 'a {
+        let span = *self;
+        self.text
+            .split_whitespace()
+            // split_whitespace hands back subslices, so their offsets fall out of the pointers
+            .map(move |word| {
+                let start = word.as_ptr() as usize - span.text.as_ptr() as usize;
+                span.slice(start, start + word.len())
+            })
+    }
+
+    /// each character along with a span pointing at it
+    pub fn chars(&self) -> impl Iterator<Item = (char, Span<'a>)> + 'a {
+        let span = *self;
+        self.text
+            .char_indices()
+            .map(move |(i, c)| (c, span.slice(i, i + c.len_utf8())))
+    }
+
+    /// parse the whole (trimmed) span as a value, `what` describing it for the error message
+    /// (like "an unsigned integer")
+    pub fn parse<T: FromStr>(&self, what: &str) -> Result<T> {
+        let trimmed = self.trim();
+        trimmed
+            .text
+            .parse::<T>()
+            .map_err(|_| trimmed.error(format!("expected {}, found {:?}", what, trimmed.text)))
+    }
+
+    /// a whitespace separated row of numbers, each one `what`
+    pub fn numbers<T: FromStr>(&self, what: &str) -> Result<Vec<T>> {
+        self.words().map(|word| word.parse(what)).collect()
+    }
+
+    /// a whitespace separated row of exactly N numbers
+    pub fn fixed_numbers<T: FromStr, const N: usize>(&self, what: &str) -> Result<[T; N]> {
+        let numbers = self.numbers::<T>(what)?;
+        let found = numbers.len();
+        numbers
+            .try_into()
+            .map_err(|_| self.error(format!("expected {} numbers, found {}", N, found)))
+    }
+
+    /// strip a `label:` prefix and return what follows it
+    pub fn label(&self, label: &str) -> Result<Span<'a>> {
+        self.expect(label)?.expect(":")
+    }
+
+    fn trim_start(&self) -> Span<'a> {
+        let start = self.text.len() - self.text.trim_start().len();
+        self.slice(start, self.text.len())
+    }
+}
+
+/// every line of the input, numbered from 1
+pub fn lines(input: &str) -> impl Iterator<Item = Span<'_>> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| Span::new(line, i + 1, 1))
+}
+
+/// groups of lines separated by blank lines
+pub fn sections(input: &str) -> Vec<Vec<Span<'_>>> {
+    let mut sections = Vec::new();
+    let mut current = Vec::new();
+    for line in lines(input) {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                sections.push(current);
+                current = Vec::new();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        sections.push(current);
+    }
+    sections
+}
+
+/// `Time:  7  15   30` or `seeds: 79 14 55 13`
+pub fn labelled_numbers<T: FromStr>(span: Span<'_>, label: &str, what: &str) -> Result<Vec<T>> {
+    span.label(label)?.numbers(what)
+}
+
+/// `Card 1: ...` or `Game 12: ...`, giving back the id and everything after the colon
+pub fn numbered_record<'a>(span: Span<'a>, label: &str) -> Result<(u32, Span<'a>)> {
+    let (head, rest) = span.split_once(":")?;
+    let id = head.expect(label)?.parse("an unsigned integer")?;
+    Ok((id, rest))
+}
+
+/// `AAA = (BBB, CCC)`, giving back the name and the items in the parentheses
+pub fn named_tuple(span: Span<'_>) -> Result<(Span<'_>, Vec<Span<'_>>)> {
+    let (name, rest) = span.split_once("=")?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(name.error("missing name before '='"));
+    }
+    let items = rest.expect("(")?.expect_suffix(")")?;
+    Ok((name, items.list(",").collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(span: Span<'_>) -> (&str, usize, usize) {
+        (span.as_str(), span.line(), span.col())
+    }
+
+    #[test]
+    fn list_pieces_start_where_they_are() {
+        let span = Span::new("3 blue,  4 red , 1 green", 2, 9);
+        let pieces = span.list(",").map(at).collect::<Vec<_>>();
+        assert_eq!(
+            pieces,
+            [("3 blue", 2, 9), ("4 red", 2, 18), ("1 green", 2, 26)]
+        );
+        // separators longer than a byte move the offsets on by their whole length
+        let pieces = Span::new("a; b;c", 1, 1)
+            .list("; ")
+            .map(at)
+            .collect::<Vec<_>>();
+        assert_eq!(pieces, [("a", 1, 1), ("b;c", 1, 4)]);
+    }
+
+    #[test]
+    fn words_start_where_they_are() {
+        let words = Span::new("  41 48\t83  86", 1, 10)
+            .words()
+            .map(at)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            words,
+            [("41", 1, 12), ("48", 1, 15), ("83", 1, 18), ("86", 1, 22)]
+        );
+    }
+
+    #[test]
+    fn split_once_keeps_both_sides_in_place() {
+        let (head, rest) = Span::new("Card 12: 41 48", 3, 1).split_once(":").unwrap();
+        assert_eq!(at(head), ("Card 12", 3, 1));
+        assert_eq!(at(rest), (" 41 48", 3, 9));
+        let err = Span::new("Card 12 41", 3, 5).split_once(":").unwrap_err();
+        assert_eq!((err.line, err.col), (3, 5));
+    }
+
+    #[test]
+    fn expect_suffix_keeps_the_start() {
+        let inner = Span::new("(BBB, CCC)  ", 4, 7)
+            .expect("(")
+            .unwrap()
+            .expect_suffix(")")
+            .unwrap();
+        assert_eq!(at(inner), ("BBB, CCC", 4, 8));
+        let err = Span::new("(BBB, CCC", 4, 7).expect_suffix(")").unwrap_err();
+        assert_eq!((err.line, err.col), (4, 7));
+    }
+
+    #[test]
+    fn bad_numbers_point_at_themselves() {
+        let span = Span::new("Time:  7  x5   30", 1, 1);
+        let err = labelled_numbers::<u32>(span, "Time", "an unsigned integer").unwrap_err();
+        assert_eq!((err.line, err.col), (1, 11));
+        assert_eq!(err.msg, "expected an unsigned integer, found \"x5\"");
+
+        let row = Span::new("50 98 2", 5, 1);
+        assert_eq!(
+            row.fixed_numbers::<u64, 3>("an unsigned integer").unwrap(),
+            [50, 98, 2]
+        );
+        let err = row
+            .fixed_numbers::<u64, 2>("an unsigned integer")
+            .unwrap_err();
+        assert_eq!((err.line, err.col), (5, 1));
+        assert_eq!(err.msg, "expected 2 numbers, found 3");
+        let err = Span::new("50 -98 2", 5, 1)
+            .fixed_numbers::<u64, 3>("an unsigned integer")
+            .unwrap_err();
+        assert_eq!((err.line, err.col), (5, 4));
+    }
+
+    #[test]
+    fn sections_ignore_crlf_and_runs_of_blank_lines() {
+        let input = "seeds: 1\r\n\r\n\r\na-to-b:\r\n1 2 3\r\n   \r\n\r\nb-to-c:\r\n4 5 6\r\n\r\n";
+        let sections = sections(input);
+        let text = sections
+            .iter()
+            .map(|s| s.iter().map(|l| l.as_str()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            text,
+            [
+                vec!["seeds: 1"],
+                vec!["a-to-b:", "1 2 3"],
+                vec!["b-to-c:", "4 5 6"]
+            ]
+        );
+        // line numbers still count the blank lines
+        assert_eq!(sections[1][1].line(), 5);
+        assert_eq!(sections[2][0].line(), 8);
+        assert!(super::sections("\n\n").is_empty());
+    }
+
+    #[test]
+    fn named_tuples_split_into_items() {
+        let (name, items) = named_tuple(Span::new("AAA = (BBB, CCC)", 1, 1)).unwrap();
+        assert_eq!(at(name), ("AAA", 1, 1));
+        assert_eq!(
+            items.into_iter().map(at).collect::<Vec<_>>(),
+            [("BBB", 1, 8), ("CCC", 1, 13)]
+        );
+        let err = named_tuple(Span::new(" = (BBB, CCC)", 2, 1)).unwrap_err();
+        assert_eq!(err.msg, "missing name before '='");
+    }
+}
@@ -0,0 +1,240 @@
+//! row by row reports for digging into how an answer came about
+//!
+//! rows are written as soon as they're handed over, so a report over a huge input
+//! doesn't need to sit in memory first
+
+use std::io::Write;
+
+use serde_json::{Map, Value};
+
+use crate::config::OutputFormat;
+
+/// one value in a report row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    Text(String),
+    Number(u64),
+    /// nothing to report, an empty csv cell or a json null
+    Empty,
+}
+
+impl From<&str> for Field {
+    fn from(text: &str) -> Self {
+        Field::Text(text.to_string())
+    }
+}
+
+impl From<u64> for Field {
+    fn from(n: u64) -> Self {
+        Field::Number(n)
+    }
+}
+
+impl From<u32> for Field {
+    fn from(n: u32) -> Self {
+        Field::Number(n.into())
+    }
+}
+
+impl From<usize> for Field {
+    fn from(n: usize) -> Self {
+        Field::Number(n as u64)
+    }
+}
+
+impl<T: Into<Field>> From<Option<T>> for Field {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Field::Empty, Into::into)
+    }
+}
+
+/// writes rows with a fixed set of columns as text, csv or a json array of objects
+pub struct ReportWriter<W: Write> {
+    out: W,
+    format: OutputFormat,
+    columns: &'static [&'static str],
+    rows: usize,
+}
+
+impl<W: Write> ReportWriter<W> {
+    pub fn new(out: W, format: OutputFormat, columns: &'static [&'static str]) -> Self {
+        ReportWriter {
+            out,
+            format,
+            columns,
+            rows: 0,
+        }
+    }
+
+    /// write one row, which must have a field for every column
+    pub fn row(&mut self, fields: Vec<Field>) -> anyhow::Result<()> {
+        assert_eq!(
+            fields.len(),
+            self.columns.len(),
+            "report row doesn't match its columns"
+        );
+        if self.rows == 0 {
+            self.header()?;
+        }
+
+        match self.format {
+            OutputFormat::Text => {
+                let cells = fields.iter().map(text_cell).collect::<Vec<_>>();
+                writeln!(self.out, "{}", cells.join("\t"))?;
+            }
+            OutputFormat::Csv => {
+                let cells = fields.iter().map(csv_cell).collect::<Vec<_>>();
+                writeln!(self.out, "{}", cells.join(","))?;
+            }
+            OutputFormat::Json => {
+                let object = self
+                    .columns
+                    .iter()
+                    .zip(fields)
+                    .map(|(column, field)| (column.to_string(), json_value(field)))
+                    .collect::<Map<_, _>>();
+                if self.rows > 0 {
+                    writeln!(self.out, ",")?;
+                }
+                write!(self.out, "  {}", Value::Object(object))?;
+            }
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// close off the report, writing the header if there weren't any rows
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        if self.rows == 0 {
+            self.header()?;
+        }
+        if self.format == OutputFormat::Json {
+            if self.rows > 0 {
+                writeln!(self.out)?;
+            }
+            writeln!(self.out, "]")?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn header(&mut self) -> anyhow::Result<()> {
+        match self.format {
+            OutputFormat::Text => writeln!(self.out, "{}", self.columns.join("\t"))?,
+            OutputFormat::Csv => writeln!(self.out, "{}", self.columns.join(","))?,
+            OutputFormat::Json => writeln!(self.out, "[")?,
+        }
+        Ok(())
+    }
+}
+
+fn text_cell(field: &Field) -> String {
+    match field {
+        Field::Text(text) => text.clone(),
+        Field::Number(n) => n.to_string(),
+        Field::Empty => "-".to_string(),
+    }
+}
+
+// quote anything that would otherwise break the row apart
+fn csv_cell(field: &Field) -> String {
+    match field {
+        Field::Text(text) if text.contains([',', '"', '\n', '\r']) => {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        }
+        Field::Text(text) => text.clone(),
+        Field::Number(n) => n.to_string(),
+        Field::Empty => String::new(),
+    }
+}
+
+fn json_value(field: Field) -> Value {
+    match field {
+        Field::Text(text) => Value::String(text),
+        Field::Number(n) => Value::from(n),
+        Field::Empty => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLUMNS: &[&str] = &["line", "word", "value"];
+
+    fn report(format: OutputFormat, rows: Vec<Vec<Field>>) -> String {
+        let mut out = Vec::new();
+        let mut writer = ReportWriter::new(&mut out, format, COLUMNS);
+        for row in rows {
+            writer.row(row).unwrap();
+        }
+        writer.finish().unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn row(line: u64, word: &str, value: Option<u32>) -> Vec<Field> {
+        vec![line.into(), word.into(), value.into()]
+    }
+
+    #[test]
+    fn csv_quotes_what_would_break_the_row() {
+        let csv = report(
+            OutputFormat::Csv,
+            vec![
+                row(1, "plain", Some(1)),
+                row(2, "a,b", Some(2)),
+                row(3, "say \"hi\"", None),
+                row(4, "two\nlines", Some(4)),
+            ],
+        );
+        assert_eq!(
+            csv,
+            "line,word,value\n1,plain,1\n2,\"a,b\",2\n3,\"say \"\"hi\"\"\",\n4,\"two\nlines\",4\n"
+        );
+    }
+
+    #[test]
+    fn json_is_one_array_however_many_rows() {
+        assert_eq!(report(OutputFormat::Json, vec![]), "[\n]\n");
+        assert_eq!(
+            report(OutputFormat::Json, vec![row(1, "one", Some(1))]),
+            "[\n  {\"line\":1,\"word\":\"one\",\"value\":1}\n]\n"
+        );
+        let many = report(
+            OutputFormat::Json,
+            (1..=3).map(|n| row(n, "x", Some(n as u32))).collect(),
+        );
+        assert_eq!(
+            many,
+            "[\n  {\"line\":1,\"word\":\"x\",\"value\":1},\n  {\"line\":2,\"word\":\"x\",\"value\":2},\n  {\"line\":3,\"word\":\"x\",\"value\":3}\n]\n"
+        );
+        let parsed: Value = serde_json::from_str(&many).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn empty_fields_in_each_format() {
+        let empty = || vec![row(7, "", None)];
+        assert_eq!(
+            report(OutputFormat::Text, empty()),
+            "line\tword\tvalue\n7\t\t-\n"
+        );
+        assert_eq!(report(OutputFormat::Csv, empty()), "line,word,value\n7,,\n");
+        assert_eq!(
+            report(OutputFormat::Json, empty()),
+            "[\n  {\"line\":7,\"word\":\"\",\"value\":null}\n]\n"
+        );
+    }
+
+    #[test]
+    fn a_report_without_rows_still_has_a_header() {
+        assert_eq!(report(OutputFormat::Text, vec![]), "line\tword\tvalue\n");
+        assert_eq!(report(OutputFormat::Csv, vec![]), "line,word,value\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "report row doesn't match its columns")]
+    fn rows_need_every_column() {
+        report(OutputFormat::Csv, vec![vec![1u64.into()]]);
+    }
+}
@@ -0,0 +1,377 @@
+//! the engine schematic from day 3: numbers, symbols and which of them touch
+//!
+//! numbers are indexed by row and sorted by column, so finding the numbers around a cell only
+//! looks at the few runs nearby rather than every number in the schematic. building the index
+//! and answering a question per symbol are both linear in the size of the schematic.
+//!
+//! positions in the grid count characters, but whether two things touch is decided by the
+//! display columns they take up (see `columns`), so tabs and wide characters line up the way
+//! they look.
+
+mod adjacency;
+mod columns;
+mod graph;
+mod live;
+mod query;
+mod render;
+
+pub use adjacency::Adjacency;
+pub use columns::{Columns, TAB_WIDTH};
+pub use graph::{Edge, Graph, GraphFormat, NumberNode, SymbolNode};
+pub use live::LiveSchematic;
+pub use query::{Aggregate, Neighbors, Query, Scope, SymbolSet};
+pub use render::Style;
+
+use crate::grid::{Grid, Pos, Run};
+use crate::parsing::{ParseError, Result};
+use columns::Layout;
+
+/// a run of digits and the number it spells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Number {
+    pub run: Run,
+    pub columns: Columns,
+    pub value: u64,
+}
+
+/// a symbol character and where it is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol {
+    pub pos: Pos,
+    pub columns: Columns,
+    pub kind: char,
+}
+
+#[derive(Debug, Clone)]
+pub struct Schematic {
+    grid: Grid<char>,
+    numbers: Vec<Number>,
+    symbols: Vec<Symbol>,
+    /// where the cells of each row show up
+    layouts: Vec<Layout>,
+    /// indices into numbers for each row, in column order
+    by_row: Vec<Vec<usize>>,
+    adjacency: Adjacency,
+}
+
+impl Schematic {
+    /// a schematic where the characters in symbols count as symbols
+    pub fn parse(input: &str, symbols: &SymbolSet) -> Result<Self> {
+        let grid = Grid::parse(input);
+
+        let mut numbers = Vec::new();
+        let mut found = Vec::new();
+        let mut layouts = Vec::with_capacity(grid.height());
+        let mut by_row = Vec::with_capacity(grid.height());
+        for row in 0..grid.height() {
+            let cells = grid.row(row).unwrap_or_default();
+            let layout = Layout::of(cells);
+            let (row_numbers, row_symbols) = scan_row(row, cells, &layout, symbols)?;
+            by_row.push((numbers.len()..numbers.len() + row_numbers.len()).collect());
+            numbers.extend(row_numbers);
+            found.extend(row_symbols);
+            layouts.push(layout);
+        }
+
+        Ok(Schematic {
+            grid,
+            numbers,
+            symbols: found,
+            layouts,
+            by_row,
+            adjacency: Adjacency::default(),
+        })
+    }
+
+    /// the same schematic with numbers counted as next to symbols by another rule
+    pub fn with_adjacency(mut self, adjacency: Adjacency) -> Self {
+        self.adjacency = adjacency;
+        self
+    }
+
+    pub fn adjacency(&self) -> Adjacency {
+        self.adjacency
+    }
+
+    pub fn grid(&self) -> &Grid<char> {
+        &self.grid
+    }
+
+    pub fn numbers(&self) -> &[Number] {
+        &self.numbers
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// the display columns of every cell in a row
+    pub fn columns(&self, row: usize) -> impl Iterator<Item = Columns> + '_ {
+        (0..self.grid.row_len(row)).map(move |col| self.layouts[row].get(col))
+    }
+
+    /// how many display columns a row takes up
+    pub fn row_width(&self, row: usize) -> usize {
+        self.layouts
+            .get(row)
+            .map_or(0, |layout| layout.width(self.grid.row_len(row)))
+    }
+
+    /// indices of the numbers next to a symbol, by this schematic's adjacency rule
+    pub fn numbers_around(&self, symbol: &Symbol) -> Vec<usize> {
+        let (row, _) = symbol.pos;
+        let reach = self.adjacency.reach();
+        let mut found = Vec::new();
+        for r in row.saturating_sub(reach)..=row + reach {
+            let Some(in_row) = self.by_row.get(r) else {
+                continue;
+            };
+            let near = within_reach(in_row, |&n| self.numbers[n].columns, symbol.columns, reach);
+            found.extend(near.iter().copied().filter(|&n| {
+                self.adjacency
+                    .touches(r.abs_diff(row), self.numbers[n].columns, symbol.columns)
+            }));
+        }
+        found
+    }
+}
+
+/// the numbers and symbols in one row of cells, laid out as given
+fn scan_row(
+    row: usize,
+    cells: &[char],
+    layout: &Layout,
+    symbols: &SymbolSet,
+) -> Result<(Vec<Number>, Vec<Symbol>)> {
+    let mut numbers = Vec::new();
+    let mut start = 0;
+    for chunk in cells.chunk_by(|a, b| a.is_ascii_digit() == b.is_ascii_digit()) {
+        let end = start + chunk.len();
+        if chunk[0].is_ascii_digit() {
+            let digits = chunk.iter().collect::<String>();
+            let value = digits.parse::<u64>().map_err(|_| ParseError {
+                line: row + 1,
+                col: layout.get(start).start + 1,
+                msg: format!("{} is too big for a part number", digits),
+            })?;
+            numbers.push(Number {
+                run: Run { row, start, end },
+                columns: Columns {
+                    start: layout.get(start).start,
+                    end: layout.get(end - 1).end,
+                },
+                value,
+            });
+        }
+        start = end;
+    }
+
+    let symbols = cells
+        .iter()
+        .enumerate()
+        .filter(|(_, &c)| symbols.contains(c))
+        .map(|(col, &kind)| Symbol {
+            pos: (row, col),
+            columns: layout.get(col),
+            kind,
+        })
+        .collect();
+
+    Ok((numbers, symbols))
+}
+
+/// the stretch of a row's items, in column order, that could be within reach of target
+///
+/// things in a row don't overlap, so sorting them by start sorts them by end too
+fn within_reach<T>(
+    items: &[T],
+    columns: impl Fn(&T) -> Columns,
+    target: Columns,
+    reach: usize,
+) -> &[T] {
+    // a zero width cell (a lone combining mark) still sits in a column
+    let last = |c: Columns| c.start.max(c.end.saturating_sub(1));
+    let first = items.partition_point(|item| last(columns(item)) + reach < target.start);
+    let len = items[first..].partition_point(|item| columns(item).start <= last(target) + reach);
+    &items[first..first + len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // any letter would be a symbol by default, these keep the cjk ones out of it
+    fn symbols() -> SymbolSet {
+        SymbolSet::Only(vec!['*', '#', '＃'])
+    }
+
+    fn part_sum(input: &str) -> u128 {
+        Schematic::parse(input, &symbols())
+            .unwrap()
+            .query(&Query::part_numbers())
+            .unwrap()
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        // 123 is in columns 2 to 4, one character in but two columns over
+        assert_eq!(part_sum("日123\n*"), 0);
+        assert_eq!(part_sum("日123\n.*"), 123);
+        assert_eq!(part_sum("日本*7"), 7);
+        // a wide symbol reaches numbers on either side of both its columns
+        assert_eq!(part_sum("..4\n＃\n..5"), 9);
+        assert_eq!(part_sum("...4\n＃"), 0);
+    }
+
+    #[test]
+    fn tabs_run_to_the_next_stop() {
+        assert_eq!(part_sum("\t5\n.......*"), 5);
+        assert_eq!(part_sum("\t5\n*"), 0);
+        assert_eq!(part_sum("ab\t5\n.......#"), 5);
+        assert_eq!(part_sum("12\t*"), 0);
+        // a tab is always at least a column wide
+        assert_eq!(part_sum("1234567\t*"), 0);
+    }
+
+    #[test]
+    fn combining_marks_share_a_column() {
+        let accented = "e\u{301}\u{301}\u{301}.7\n..*";
+        assert_eq!(part_sum(accented), 7);
+        assert_eq!(part_sum("ü*3"), 3);
+        assert_eq!(part_sum("u\u{308}.*3"), 3);
+        assert_eq!(part_sum("u\u{308}3\n..*"), 3);
+    }
+
+    #[test]
+    fn graph_positions_are_display_columns() {
+        let schematic = Schematic::parse("日本\t42\n日本語*", &symbols()).unwrap();
+        let graph = schematic.graph();
+        assert_eq!((graph.numbers[0].line, graph.numbers[0].col), (1, 9));
+        assert_eq!((graph.symbols[0].line, graph.symbols[0].col), (2, 7));
+        assert!(graph.edges.is_empty());
+    }
+
+    #[test]
+    fn plain_markers_line_up_under_wide_characters() {
+        let schematic = Schematic::parse("日12\n*.3", &symbols()).unwrap();
+        let rendered = schematic.render(Style::Plain, &Query::gear_ratios());
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(lines[..4], ["日12", "  xx", "*.3", "  x"]);
+    }
+
+    /// xorshift, enough randomness for generating schematics without another dependency
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    /// a schematic of mostly digits and dots, with some symbols, tabs and wide characters
+    fn random_schematic(rng: &mut Rng, rows: usize, cols: usize) -> String {
+        const CELLS: [char; 8] = ['.', '.', '.', '*', '#', '\t', '日', 'e'];
+        (0..rows)
+            .map(|_| {
+                // ragged rows, some of them empty
+                (0..rng.below(cols + 1))
+                    .map(|_| match rng.below(10) {
+                        0..=3 => char::from(b'0' + rng.below(10) as u8),
+                        _ => CELLS[rng.below(CELLS.len())],
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// numbers_around the slow way, every number against the symbol
+    fn naive_around(schematic: &Schematic, symbol: &Symbol) -> Vec<usize> {
+        let adjacency = schematic.adjacency();
+        (0..schematic.numbers().len())
+            .filter(|&n| {
+                let number = &schematic.numbers()[n];
+                let rows = number.run.row.abs_diff(symbol.pos.0);
+                adjacency.touches(rows, number.columns, symbol.columns)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn numbers_around_matches_checking_every_number() {
+        let adjacencies = [
+            Adjacency::Chebyshev(1),
+            Adjacency::Chebyshev(2),
+            Adjacency::Manhattan(1),
+            Adjacency::Manhattan(3),
+            Adjacency::Diagonal,
+        ];
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..300 {
+            let rows = 1 + rng.below(8);
+            let input = random_schematic(&mut rng, rows, 12);
+            for adjacency in adjacencies {
+                let schematic = Schematic::parse(&input, &symbols())
+                    .unwrap()
+                    .with_adjacency(adjacency);
+                for symbol in schematic.symbols() {
+                    let mut around = schematic.numbers_around(symbol);
+                    around.sort();
+                    assert_eq!(
+                        around,
+                        naive_around(&schematic, symbol),
+                        "{:?} around {:?} in\n{}",
+                        adjacency,
+                        symbol.pos,
+                        input
+                    );
+                }
+
+                // and the sums kept by a live schematic agree with the queries
+                let live = LiveSchematic::new(&input, symbols(), adjacency).unwrap();
+                assert_eq!(
+                    live.part_sum(),
+                    schematic.query(&Query::part_numbers()).unwrap()
+                );
+                assert_eq!(
+                    live.gear_sum(),
+                    schematic.query(&Query::gear_ratios()).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "slow and needs about 2 GB, run in release with --ignored to check the linear time claim"]
+    fn big_schematics_take_linear_time() {
+        let time = |side: usize| {
+            let mut rng = Rng(side as u64);
+            let input = random_schematic(&mut rng, side, side);
+            let start = std::time::Instant::now();
+            let schematic = Schematic::parse(&input, &symbols()).unwrap();
+            schematic.query(&Query::part_numbers()).unwrap();
+            schematic.query(&Query::gear_ratios()).unwrap();
+            start.elapsed()
+        };
+        // warm up, then four times the cells should take about four times as long rather
+        // than the sixteen a quadratic search would. 10k by 10k is a 100 MB input, the size
+        // the claim is about
+        time(1000);
+        let small = time(5000);
+        let big = time(10_000);
+        assert!(big < small * 8, "{:?} then {:?}", small, big);
+
+        // one long row is the worst case for a search that walks the whole row
+        let row = "12*.".repeat(250_000);
+        let start = std::time::Instant::now();
+        let schematic = Schematic::parse(&row, &symbols()).unwrap();
+        assert_eq!(
+            schematic.query(&Query::part_numbers()).unwrap(),
+            12 * 250_000
+        );
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    }
+}
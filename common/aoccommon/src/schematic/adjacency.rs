@@ -0,0 +1,139 @@
+//! what counts as a number being next to a symbol
+//!
+//! the puzzle's rule is the eight cells around a symbol, but variants want only the cells
+//! sharing an edge, only the corners, or everything within some distance. distances are
+//! between the closest pair of cells, one from the number and one from the symbol, with
+//! columns counted in display columns like everything else.
+
+use std::str::FromStr;
+
+use super::Columns;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Adjacency {
+    /// no more than this many rows and columns away, 1 being the eight cells around
+    Chebyshev(usize),
+    /// no more than this many steps away going up, down, left and right, 1 being the four
+    /// cells sharing an edge
+    Manhattan(usize),
+    /// only the four cells across a corner
+    Diagonal,
+}
+
+impl Default for Adjacency {
+    fn default() -> Self {
+        Adjacency::Chebyshev(1)
+    }
+}
+
+impl Adjacency {
+    /// the furthest a number can be from a symbol in rows or columns and still count
+    pub fn reach(&self) -> usize {
+        match *self {
+            Adjacency::Chebyshev(radius) | Adjacency::Manhattan(radius) => radius,
+            Adjacency::Diagonal => 1,
+        }
+    }
+
+    /// whether a number covering `number` is next to a symbol covering `symbol`, rows apart
+    pub fn touches(&self, rows: usize, number: Columns, symbol: Columns) -> bool {
+        // a zero width symbol (a lone combining mark) still sits in a column
+        let symbol_last = symbol.start.max(symbol.end.saturating_sub(1));
+        let number_last = number.end - 1;
+        let cols = symbol
+            .start
+            .saturating_sub(number_last)
+            .max(number.start.saturating_sub(symbol_last));
+
+        match *self {
+            Adjacency::Chebyshev(radius) => rows <= radius && cols <= radius,
+            Adjacency::Manhattan(radius) => rows + cols <= radius,
+            Adjacency::Diagonal => {
+                // some digit has to be one column either side of some cell of the symbol
+                let left = symbol.start.checked_sub(1);
+                let right = symbol_last + 1;
+                let wide = symbol_last > symbol.start;
+                let covers = |col: usize| number.start <= col && col <= number_last;
+                rows == 1
+                    && (left.is_some_and(covers)
+                        || covers(right)
+                        || (wide && number.start <= symbol_last && symbol.start <= number_last))
+            }
+        }
+    }
+}
+
+/// `around` (the puzzle's eight cells), `orthogonal`, `diagonal`, `chebyshev:N` or
+/// `manhattan:N`
+impl FromStr for Adjacency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let radius = |r: &str| {
+            r.trim()
+                .parse()
+                .map_err(|_| format!("expected a radius after the colon, found {:?}", r))
+        };
+        match s.split_once(':') {
+            None => match s.as_str() {
+                "around" => Ok(Adjacency::Chebyshev(1)),
+                "orthogonal" => Ok(Adjacency::Manhattan(1)),
+                "diagonal" => Ok(Adjacency::Diagonal),
+                _ => Err(format!(
+                    "unknown adjacency {:?}, expected around, orthogonal, diagonal, \
+                     chebyshev:N or manhattan:N",
+                    s
+                )),
+            },
+            Some(("chebyshev", r)) => Ok(Adjacency::Chebyshev(radius(r)?)),
+            Some(("manhattan", r)) => Ok(Adjacency::Manhattan(radius(r)?)),
+            Some((name, _)) => Err(format!(
+                "unknown distance {:?}, expected chebyshev or manhattan",
+                name
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cols(start: usize, end: usize) -> Columns {
+        Columns { start, end }
+    }
+
+    #[test]
+    fn modes_parse() {
+        assert_eq!("around".parse(), Ok(Adjacency::Chebyshev(1)));
+        assert_eq!("Orthogonal".parse(), Ok(Adjacency::Manhattan(1)));
+        assert_eq!("diagonal".parse(), Ok(Adjacency::Diagonal));
+        assert_eq!("chebyshev:3".parse(), Ok(Adjacency::Chebyshev(3)));
+        assert_eq!("manhattan: 2".parse(), Ok(Adjacency::Manhattan(2)));
+        assert!("manhattan".parse::<Adjacency>().is_err());
+        assert!("euclid:2".parse::<Adjacency>().is_err());
+    }
+
+    #[test]
+    fn diagonal_only_counts_corners() {
+        // a number covering columns 2 to 4, a symbol in column 3 or beside it
+        let number = cols(2, 5);
+        assert!(!Adjacency::Diagonal.touches(0, number, cols(5, 6)));
+        assert!(Adjacency::Diagonal.touches(1, number, cols(5, 6)));
+        assert!(Adjacency::Diagonal.touches(1, number, cols(3, 4)));
+        assert!(!Adjacency::Diagonal.touches(1, cols(3, 4), cols(3, 4)));
+        assert!(!Adjacency::Diagonal.touches(1, number, cols(6, 7)));
+        // a wide symbol has corners on both of its columns
+        assert!(Adjacency::Diagonal.touches(1, cols(3, 4), cols(3, 5)));
+    }
+
+    #[test]
+    fn radius_counts_the_closest_cells() {
+        let number = cols(2, 5);
+        assert!(Adjacency::Manhattan(2).touches(1, number, cols(5, 6)));
+        assert!(!Adjacency::Manhattan(2).touches(1, number, cols(6, 7)));
+        assert!(Adjacency::Chebyshev(2).touches(2, number, cols(6, 7)));
+        assert!(!Adjacency::Chebyshev(2).touches(3, number, cols(6, 7)));
+    }
+}
@@ -0,0 +1,115 @@
+//! where each character of a schematic shows up on screen
+//!
+//! adjacency has to match what a person looking at the schematic sees, so it works in display
+//! columns rather than characters or bytes. a grapheme cluster takes as many columns as it's
+//! wide (two for most cjk characters, one for an accented letter however many code points it
+//! is made of) and a tab runs on to the next tab stop.
+//!
+//! most schematics are plain ascii, where a character's column is just its index, so a row's
+//! layout is only worked out and kept when something in it is wider, narrower or a tab.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// how far apart tab stops are
+pub const TAB_WIDTH: usize = 8;
+
+/// the display columns something covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Columns {
+    pub start: usize,
+    /// exclusive
+    pub end: usize,
+}
+
+impl Columns {
+    pub fn width(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// where each cell of a row shows up
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    /// the columns of every cell, None when every cell is one column wide
+    cells: Option<Vec<Columns>>,
+}
+
+impl Layout {
+    pub fn of(cells: &[char]) -> Self {
+        // printable ascii is one column per character and one character per grapheme
+        let plain = cells.iter().all(|c| matches!(c, ' '..='~'));
+        Layout {
+            cells: (!plain).then(|| layout(&cells.iter().collect::<String>())),
+        }
+    }
+
+    /// the columns of the cell at index col
+    pub fn get(&self, col: usize) -> Columns {
+        match &self.cells {
+            Some(cells) => cells[col],
+            None => Columns {
+                start: col,
+                end: col + 1,
+            },
+        }
+    }
+
+    /// how wide a row of len cells looks
+    pub fn width(&self, len: usize) -> usize {
+        match &self.cells {
+            Some(cells) => cells.last().map_or(0, |c| c.end),
+            None => len,
+        }
+    }
+}
+
+/// the columns of every character in a line, the characters of one grapheme cluster all
+/// getting the columns of the whole cluster
+fn layout(line: &str) -> Vec<Columns> {
+    let mut columns = Vec::with_capacity(line.len());
+    let mut col = 0;
+    for grapheme in line.graphemes(true) {
+        let width = if grapheme == "\t" {
+            TAB_WIDTH - col % TAB_WIDTH
+        } else {
+            grapheme.width()
+        };
+        let cluster = Columns {
+            start: col,
+            end: col + width,
+        };
+        columns.extend(grapheme.chars().map(|_| cluster));
+        col += width;
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_rows_skip_the_layout() {
+        let line = "467..114.. *#$+/=-@&%";
+        let cells = line.chars().collect::<Vec<_>>();
+        let plain = Layout::of(&cells);
+        assert_eq!(plain.cells, None);
+        // and still agree with working it out
+        let full = layout(line);
+        assert!((0..cells.len()).all(|col| plain.get(col) == full[col]));
+        assert_eq!(plain.width(cells.len()), cells.len());
+    }
+
+    #[test]
+    fn rows_with_tabs_or_unicode_keep_theirs() {
+        for line in ["1\t*", "日1*", "e\u{301}1"] {
+            let cells = line.chars().collect::<Vec<_>>();
+            assert!(Layout::of(&cells).cells.is_some(), "{:?}", line);
+        }
+        let cells = "日\t1".chars().collect::<Vec<_>>();
+        let layout = Layout::of(&cells);
+        assert_eq!(layout.get(2), Columns { start: 8, end: 9 });
+        assert_eq!(layout.width(cells.len()), 9);
+    }
+}
@@ -0,0 +1,244 @@
+//! the schematic as a graph of numbers and symbols, joined wherever they touch
+//!
+//! the graph only has edges between a symbol and a number, so questions like which symbols
+//! share a number or which numbers touch more than one symbol are a step along the edges.
+//! part 1 is the sum of every number with an edge, part 2 multiplies the two numbers of every
+//! `*` with exactly two edges. positions are 1 based lines and display columns.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use super::Schematic;
+
+/// how the graph is written out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// graphviz, for `dot -Tsvg` and friends
+    #[default]
+    Dot,
+    Json,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dot" => Ok(GraphFormat::Dot),
+            "json" => Ok(GraphFormat::Json),
+            _ => Err(format!(
+                "unknown graph format {:?}, expected dot or json",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Graph {
+    pub numbers: Vec<NumberNode>,
+    pub symbols: Vec<SymbolNode>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NumberNode {
+    /// `n` and the number's index
+    pub id: String,
+    pub value: u64,
+    pub line: usize,
+    /// where the first digit is
+    pub col: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SymbolNode {
+    /// `s` and the symbol's index
+    pub id: String,
+    pub kind: char,
+    pub line: usize,
+    pub col: usize,
+}
+
+/// a symbol and a number that touch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Edge {
+    pub symbol: String,
+    pub number: String,
+}
+
+impl Schematic {
+    /// every number and symbol, with an edge for each pair that touch
+    pub fn graph(&self) -> Graph {
+        let numbers = self
+            .numbers()
+            .iter()
+            .enumerate()
+            .map(|(n, number)| NumberNode {
+                id: format!("n{}", n),
+                value: number.value,
+                line: number.run.row + 1,
+                col: number.columns.start + 1,
+                len: number.run.len(),
+            })
+            .collect();
+        let symbols = self
+            .symbols()
+            .iter()
+            .enumerate()
+            .map(|(s, symbol)| SymbolNode {
+                id: format!("s{}", s),
+                kind: symbol.kind,
+                line: symbol.pos.0 + 1,
+                col: symbol.columns.start + 1,
+            })
+            .collect();
+        let edges = self
+            .symbols()
+            .iter()
+            .enumerate()
+            .flat_map(|(s, symbol)| {
+                self.numbers_around(symbol).into_iter().map(move |n| Edge {
+                    symbol: format!("s{}", s),
+                    number: format!("n{}", n),
+                })
+            })
+            .collect();
+
+        Graph {
+            numbers,
+            symbols,
+            edges,
+        }
+    }
+}
+
+impl Graph {
+    pub fn write(&self, format: GraphFormat) -> anyhow::Result<String> {
+        match format {
+            GraphFormat::Dot => Ok(self.to_dot()),
+            GraphFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    /// an undirected graphviz graph, numbers as boxes and symbols as circles
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("graph schematic {\n");
+        for number in &self.numbers {
+            // writing to a String can't fail
+            let _ = writeln!(
+                out,
+                "    {} [shape=box, label=\"{}\", value={}, line={}, col={}, len={}];",
+                number.id, number.value, number.value, number.line, number.col, number.len
+            );
+        }
+        for symbol in &self.symbols {
+            let _ = writeln!(
+                out,
+                "    {} [shape=circle, label=\"{}\", line={}, col={}];",
+                symbol.id,
+                escape(symbol.kind),
+                symbol.line,
+                symbol.col
+            );
+        }
+        for edge in &self.edges {
+            let _ = writeln!(out, "    {} -- {};", edge.symbol, edge.number);
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// a symbol as it can go inside a quoted dot string
+fn escape(kind: char) -> String {
+    match kind {
+        '"' | '\\' => format!("\\{}", kind),
+        _ => kind.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schematic::SymbolSet;
+
+    const EXAMPLE: &str = "\
+467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..";
+
+    fn graph(input: &str) -> Graph {
+        Schematic::parse(input, &SymbolSet::Default)
+            .unwrap()
+            .graph()
+    }
+
+    #[test]
+    fn both_answers_fall_out_of_the_edges() {
+        let graph = graph(EXAMPLE);
+        let value = |id: &str| {
+            graph
+                .numbers
+                .iter()
+                .find(|number| number.id == id)
+                .unwrap()
+                .value
+        };
+
+        let mut parts = graph
+            .edges
+            .iter()
+            .map(|edge| edge.number.as_str())
+            .collect::<Vec<_>>();
+        parts.sort();
+        parts.dedup();
+        assert_eq!(parts.iter().map(|&n| value(n)).sum::<u64>(), 4361);
+
+        let ratios = graph
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind == '*')
+            .map(|symbol| {
+                graph
+                    .edges
+                    .iter()
+                    .filter(|edge| edge.symbol == symbol.id)
+                    .map(|edge| value(&edge.number))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|around| around.len() == 2)
+            .map(|around| around[0] * around[1])
+            .sum::<u64>();
+        assert_eq!(ratios, 467835);
+    }
+
+    #[test]
+    fn dot_escapes_quotes_and_backslashes() {
+        let dot = graph("1\"\n\n2\\").to_dot();
+        assert!(dot.contains("s0 [shape=circle, label=\"\\\"\", line=1, col=2];"));
+        assert!(dot.contains("s1 [shape=circle, label=\"\\\\\", line=3, col=2];"));
+        assert!(dot.contains("    s0 -- n0;\n    s1 -- n1;\n"));
+        assert!(dot.starts_with("graph schematic {\n") && dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn json_lists_nodes_and_edges() {
+        let json = graph("12*").write(GraphFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["numbers"][0]["value"], 12);
+        assert_eq!(value["symbols"][0]["kind"], "*");
+        assert_eq!(value["edges"][0]["symbol"], "s0");
+        assert_eq!(value["edges"][0]["number"], "n0");
+    }
+}
@@ -0,0 +1,204 @@
+//! a schematic that can be edited a cell at a time, keeping both answers up to date
+//!
+//! changing a cell only changes the numbers and symbols on its own row, so the only numbers
+//! that can become (or stop being) part numbers and the only gears that can change are the
+//! ones within reach of that row. an edit takes back what those rows added to the sums,
+//! rescans the edited row and adds their share back, so it costs the size of a few rows
+//! whatever the size of the schematic.
+
+use std::fmt;
+
+use super::{scan_row, within_reach, Adjacency, Layout, Number, Symbol, SymbolSet};
+use crate::grid::Pos;
+
+/// what a blank cell is filled with when an edit lands past the end of a row
+const FILLER: char = '.';
+
+/// the part number sum and gear ratio sum of a schematic that's being edited
+///
+/// these are the answers to `Query::part_numbers` and `Query::gear_ratios`, kept current
+#[derive(Debug, Clone)]
+pub struct LiveSchematic {
+    symbols: SymbolSet,
+    adjacency: Adjacency,
+    rows: Vec<Row>,
+    part_sum: u128,
+    gear_sum: u128,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Row {
+    cells: Vec<char>,
+    numbers: Vec<Number>,
+    symbols: Vec<Symbol>,
+}
+
+impl LiveSchematic {
+    pub fn new(input: &str, symbols: SymbolSet, adjacency: Adjacency) -> anyhow::Result<Self> {
+        let mut live = LiveSchematic {
+            symbols,
+            adjacency,
+            rows: Vec::new(),
+            part_sum: 0,
+            gear_sum: 0,
+        };
+        for line in input.lines() {
+            let row = live.scan(live.rows.len(), line.chars().collect())?;
+            live.rows.push(row);
+        }
+        (live.part_sum, live.gear_sum) = live.share(0, live.rows.len()).ok_or_else(too_big)?;
+        Ok(live)
+    }
+
+    /// the sum of every number next to a symbol
+    pub fn part_sum(&self) -> u128 {
+        self.part_sum
+    }
+
+    /// the sum of every gear ratio, a gear being a `*` next to exactly two numbers
+    pub fn gear_sum(&self) -> u128 {
+        self.gear_sum
+    }
+
+    pub fn get(&self, (row, col): Pos) -> Option<char> {
+        self.rows.get(row)?.cells.get(col).copied()
+    }
+
+    /// put c at pos, growing the schematic with blank cells if pos is outside it
+    ///
+    /// a failed edit (a number getting too long, or a sum too big) leaves the schematic as
+    /// it was
+    pub fn set(&mut self, (row, col): Pos, c: char) -> anyhow::Result<()> {
+        if c == '\n' || c == '\r' {
+            anyhow::bail!("a cell can't hold a line break");
+        }
+        let mut cells = self
+            .rows
+            .get(row)
+            .map_or_else(Vec::new, |r| r.cells.clone());
+        if cells.len() <= col {
+            cells.resize(col + 1, FILLER);
+        }
+        cells[col] = c;
+        let edited = self.scan(row, cells)?;
+
+        let height = self.rows.len();
+        if height <= row {
+            self.rows.resize_with(row + 1, Row::default);
+        }
+
+        let reach = self.adjacency.reach();
+        let (low, high) = (
+            row.saturating_sub(reach),
+            (row + reach + 1).min(self.rows.len()),
+        );
+        // the rows around the edit were counted in the sums, so their share always fits
+        let (old_parts, old_gears) = self.share(low, high).ok_or_else(too_big)?;
+        let original = std::mem::replace(&mut self.rows[row], edited);
+        let sums = self.share(low, high).and_then(|(parts, gears)| {
+            Some((
+                (self.part_sum - old_parts).checked_add(parts)?,
+                (self.gear_sum - old_gears).checked_add(gears)?,
+            ))
+        });
+        match sums {
+            Some((parts, gears)) => {
+                (self.part_sum, self.gear_sum) = (parts, gears);
+                Ok(())
+            }
+            None => {
+                self.rows[row] = original;
+                self.rows.truncate(height);
+                Err(too_big())
+            }
+        }
+    }
+
+    fn scan(&self, row: usize, cells: Vec<char>) -> anyhow::Result<Row> {
+        let (numbers, symbols) = scan_row(row, &cells, &Layout::of(&cells), &self.symbols)?;
+        Ok(Row {
+            cells,
+            numbers,
+            symbols,
+        })
+    }
+
+    /// what the numbers and gears on rows low to high (exclusive) add to the two sums,
+    /// None if that doesn't fit in a u128
+    fn share(&self, low: usize, high: usize) -> Option<(u128, u128)> {
+        let mut parts = 0u128;
+        let mut gears = 0u128;
+        for row in &self.rows[low..high] {
+            for number in &row.numbers {
+                if self.next_to_symbol(number) {
+                    parts = parts.checked_add(number.value.into())?;
+                }
+            }
+            for symbol in row.symbols.iter().filter(|symbol| symbol.kind == '*') {
+                if let [a, b] = self.numbers_around(symbol)[..] {
+                    gears = gears.checked_add(u128::from(a) * u128::from(b))?;
+                }
+            }
+        }
+        Some((parts, gears))
+    }
+
+    /// the values of the numbers next to a symbol
+    fn numbers_around(&self, symbol: &Symbol) -> Vec<u64> {
+        self.rows_around(symbol.pos.0)
+            .flat_map(|(rows, row)| {
+                within_reach(
+                    &row.numbers,
+                    |number| number.columns,
+                    symbol.columns,
+                    self.adjacency.reach(),
+                )
+                .iter()
+                .filter(move |number| self.adjacency.touches(rows, number.columns, symbol.columns))
+                .map(|number| number.value)
+            })
+            .collect()
+    }
+
+    fn next_to_symbol(&self, number: &Number) -> bool {
+        self.rows_around(number.run.row).any(|(rows, row)| {
+            within_reach(
+                &row.symbols,
+                |symbol| symbol.columns,
+                number.columns,
+                self.adjacency.reach(),
+            )
+            .iter()
+            .any(|symbol| self.adjacency.touches(rows, number.columns, symbol.columns))
+        })
+    }
+
+    /// the rows within reach of a row, with how far away each is
+    fn rows_around(&self, row: usize) -> impl Iterator<Item = (usize, &Row)> {
+        let reach = self.adjacency.reach();
+        let low = row.saturating_sub(reach);
+        self.rows
+            .iter()
+            .enumerate()
+            .skip(low)
+            .take(row + reach + 1 - low)
+            .map(move |(r, cells)| (r.abs_diff(row), cells))
+    }
+}
+
+/// the schematic as text, one line per row
+impl fmt::Display for LiveSchematic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, row) in self.rows.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", row.cells.iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+fn too_big() -> anyhow::Error {
+    anyhow::anyhow!("the answer is too big to fit in 128 bits")
+}
@@ -0,0 +1,268 @@
+//! questions of the form "which numbers sit around which symbols, and what do they add up to"
+//!
+//! part 1 is every number next to any symbol, summed. part 2 is every `*` with exactly two
+//! numbers around it, multiplied per gear and then summed. both are just settings here.
+
+use std::str::FromStr;
+
+use super::Schematic;
+
+/// which characters count as symbols
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SymbolSet {
+    /// anything that isn't a digit, a dot or whitespace
+    #[default]
+    Default,
+    /// only these characters (digits never count)
+    Only(Vec<char>),
+}
+
+impl SymbolSet {
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            SymbolSet::Default => !(c.is_ascii_digit() || c == '.' || c.is_whitespace()),
+            SymbolSet::Only(chars) => !c.is_ascii_digit() && chars.contains(&c),
+        }
+    }
+}
+
+impl FromStr for SymbolSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("expected at least one symbol character".to_string());
+        }
+        Ok(SymbolSet::Only(s.chars().collect()))
+    }
+}
+
+/// how many numbers a symbol needs around it to match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Neighbors {
+    Exactly(usize),
+    AtLeast(usize),
+    AtMost(usize),
+}
+
+impl Neighbors {
+    pub fn allows(&self, count: usize) -> bool {
+        match *self {
+            Neighbors::Exactly(n) => count == n,
+            Neighbors::AtLeast(n) => count >= n,
+            Neighbors::AtMost(n) => count <= n,
+        }
+    }
+}
+
+/// `2` or `=2` for exactly, `>=1` for at least, `<=3` for at most
+impl FromStr for Neighbors {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (make, n): (fn(usize) -> Neighbors, &str) = if let Some(n) = s.strip_prefix(">=") {
+            (Neighbors::AtLeast, n)
+        } else if let Some(n) = s.strip_prefix("<=") {
+            (Neighbors::AtMost, n)
+        } else {
+            (Neighbors::Exactly, s.strip_prefix('=').unwrap_or(s))
+        };
+        let n = n
+            .trim()
+            .parse()
+            .map_err(|_| format!("expected N, =N, >=N or <=N, found {:?}", s))?;
+        Ok(make(n))
+    }
+}
+
+/// whether numbers are combined per symbol or across every matching symbol at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// combine the numbers around each symbol, then add those results up
+    EachSymbol,
+    /// combine every number around any matching symbol, each number once
+    AllNumbers,
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "each" | "each-symbol" => Ok(Scope::EachSymbol),
+            "all" | "all-numbers" => Ok(Scope::AllNumbers),
+            _ => Err(format!("unknown scope {:?}, expected each or all", s)),
+        }
+    }
+}
+
+/// how to combine a group of numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum,
+    Product,
+    Count,
+}
+
+impl FromStr for Aggregate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sum" => Ok(Aggregate::Sum),
+            "product" => Ok(Aggregate::Product),
+            "count" => Ok(Aggregate::Count),
+            _ => Err(format!(
+                "unknown aggregate {:?}, expected sum, product or count",
+                s
+            )),
+        }
+    }
+}
+
+impl Aggregate {
+    /// None if the result doesn't fit in a u128
+    pub(super) fn apply(&self, mut values: impl Iterator<Item = u64>) -> Option<u128> {
+        match self {
+            Aggregate::Sum => values.try_fold(0u128, |acc, v| acc.checked_add(v.into())),
+            Aggregate::Product => values.try_fold(1u128, |acc, v| acc.checked_mul(v.into())),
+            Aggregate::Count => Some(values.count() as u128),
+        }
+    }
+}
+
+/// which symbols to look around and what to do with the numbers found there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    /// symbol characters to look around, every symbol if None
+    pub kinds: Option<Vec<char>>,
+    pub neighbors: Neighbors,
+    pub scope: Scope,
+    pub aggregate: Aggregate,
+}
+
+impl Query {
+    /// part 1: the sum of every number next to a symbol
+    pub fn part_numbers() -> Self {
+        Query {
+            kinds: None,
+            neighbors: Neighbors::AtLeast(1),
+            scope: Scope::AllNumbers,
+            aggregate: Aggregate::Sum,
+        }
+    }
+
+    /// part 2: the sum of every gear ratio, a gear being a `*` next to exactly two numbers
+    pub fn gear_ratios() -> Self {
+        Query {
+            kinds: Some(vec!['*']),
+            neighbors: Neighbors::Exactly(2),
+            scope: Scope::EachSymbol,
+            aggregate: Aggregate::Product,
+        }
+    }
+
+    /// whether symbols of this kind are looked around at all
+    pub fn looks_at(&self, kind: char) -> bool {
+        self.kinds
+            .as_ref()
+            .is_none_or(|kinds| kinds.contains(&kind))
+    }
+}
+
+impl Schematic {
+    /// answer a query about the numbers around this schematic's symbols
+    ///
+    /// the arithmetic is checked, an answer too big for a u128 is an error rather than
+    /// quietly wrapping around
+    pub fn query(&self, query: &Query) -> anyhow::Result<u128> {
+        let mut matching = self
+            .symbols()
+            .iter()
+            .filter(|symbol| query.looks_at(symbol.kind))
+            .map(|symbol| self.numbers_around(symbol))
+            .filter(|around| query.neighbors.allows(around.len()));
+
+        let value = |n: usize| self.numbers()[n].value;
+        let total = match query.scope {
+            Scope::EachSymbol => matching.try_fold(0u128, |acc, around| {
+                acc.checked_add(query.aggregate.apply(around.into_iter().map(value))?)
+            }),
+            Scope::AllNumbers => {
+                // numbers are already indices, so a flag per number does what a set would
+                let mut seen = vec![false; self.numbers().len()];
+                let numbers = matching
+                    .flatten()
+                    .filter(|&n| !std::mem::replace(&mut seen[n], true))
+                    .collect::<Vec<_>>();
+                query.aggregate.apply(numbers.into_iter().map(value))
+            }
+        };
+        total.ok_or_else(|| anyhow::anyhow!("the answer is too big to fit in 128 bits"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a gear per line, `a*b`, with blank lines between so they don't touch
+    fn gears(pairs: &[(u64, u64)]) -> Schematic {
+        let input = pairs
+            .iter()
+            .map(|(a, b)| format!("{}*{}", a, b))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        Schematic::parse(&input, &SymbolSet::Default).unwrap()
+    }
+
+    #[test]
+    fn ratios_past_u64_are_exact() {
+        let big = 1 << 32;
+        let schematic = gears(&vec![(big, big); 1000]);
+        let total = schematic.query(&Query::gear_ratios()).unwrap();
+        assert_eq!(total, 1000 << 64);
+    }
+
+    #[test]
+    fn sums_of_huge_part_numbers_are_exact() {
+        let schematic = gears(&vec![(u64::MAX, u64::MAX - 1); 500]);
+        let total = schematic.query(&Query::part_numbers()).unwrap();
+        assert_eq!(total, 500 * (2 * u128::from(u64::MAX) - 1));
+    }
+
+    #[test]
+    fn the_largest_ratio_fits() {
+        let schematic = gears(&[(u64::MAX, u64::MAX)]);
+        let total = schematic.query(&Query::gear_ratios()).unwrap();
+        assert_eq!(total, u128::from(u64::MAX) * u128::from(u64::MAX));
+    }
+
+    #[test]
+    fn overflowing_answers_are_errors() {
+        // each ratio fits on its own but two of them don't
+        let schematic = gears(&[(u64::MAX, u64::MAX), (u64::MAX, u64::MAX)]);
+        let err = schematic.query(&Query::gear_ratios()).unwrap_err();
+        assert_eq!(err.to_string(), "the answer is too big to fit in 128 bits");
+
+        // three big numbers multiplied together overflow inside one group
+        let schematic =
+            Schematic::parse(&format!("{0}*{0}\n.*{0}", u64::MAX), &SymbolSet::Default).unwrap();
+        let product = Query {
+            scope: Scope::AllNumbers,
+            aggregate: Aggregate::Product,
+            ..Query::part_numbers()
+        };
+        assert!(schematic.query(&product).is_err());
+    }
+
+    #[test]
+    fn numbers_past_u64_are_parse_errors() {
+        let input = format!("{}0*1", u64::MAX);
+        let err = Schematic::parse(&input, &SymbolSet::Default).unwrap_err();
+        assert_eq!(err.line, 1);
+        assert_eq!(err.col, 1);
+        assert!(err.msg.ends_with("is too big for a part number"));
+    }
+}
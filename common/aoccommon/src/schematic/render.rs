@@ -0,0 +1,315 @@
+//! drawing a schematic with its numbers and gears marked, for chasing down adjacency mistakes
+//!
+//! part numbers, numbers with no symbol around them and the symbols the query matches each get
+//! their own color. when the query combines numbers per symbol, every row with a matching symbol
+//! has that symbol's result written beside it, so the panels add up to the answer. without colors
+//! (say when the output goes to a file) a line of markers under each row does the same job.
+
+use std::env;
+use std::io::{self, IsTerminal};
+use std::iter;
+use std::str::FromStr;
+
+use super::{Aggregate, Query, Schematic, Scope};
+
+const RESET: &str = "\x1b[0m";
+
+/// how marks are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// ansi colors on the schematic itself
+    Color,
+    /// the schematic as is with a line of markers under each row
+    Plain,
+}
+
+impl Style {
+    /// colors when stdout is a terminal and NO_COLOR isn't set, markers otherwise
+    pub fn detect() -> Self {
+        if io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none() {
+            Style::Color
+        } else {
+            Style::Plain
+        }
+    }
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "color" | "colour" => Ok(Style::Color),
+            "plain" => Ok(Style::Plain),
+            _ => Err(format!("unknown style {:?}, expected color or plain", s)),
+        }
+    }
+}
+
+/// what a cell turned out to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Blank,
+    /// a digit of a number with a symbol around it
+    Part,
+    /// a digit of a number with no symbol around it
+    Loose,
+    /// a symbol the query matches (a gear for part 2)
+    Match,
+    Symbol,
+}
+
+impl Mark {
+    fn color(self) -> Option<&'static str> {
+        match self {
+            Mark::Blank => None,
+            Mark::Part => Some("\x1b[32m"),
+            Mark::Loose => Some("\x1b[31m"),
+            Mark::Match => Some("\x1b[1;33m"),
+            Mark::Symbol => Some("\x1b[36m"),
+        }
+    }
+
+    fn marker(self) -> char {
+        match self {
+            Mark::Blank | Mark::Symbol => ' ',
+            Mark::Part => '^',
+            Mark::Loose => 'x',
+            Mark::Match => 'M',
+        }
+    }
+}
+
+impl Schematic {
+    /// the whole schematic with part numbers, loose numbers and the symbols the query matches marked
+    pub fn render(&self, style: Style, query: &Query) -> String {
+        let grid = self.grid();
+        let mut marks = (0..grid.height())
+            .map(|row| vec![Mark::Blank; grid.row_len(row)])
+            .collect::<Vec<_>>();
+        let mut is_part = vec![false; self.numbers().len()];
+        // what every matching symbol adds to the answer, by row
+        let mut panels = vec![Vec::new(); grid.height()];
+
+        for symbol in self.symbols() {
+            let around = self.numbers_around(symbol);
+            for &n in &around {
+                is_part[n] = true;
+            }
+            let (row, col) = symbol.pos;
+            if query.looks_at(symbol.kind) && query.neighbors.allows(around.len()) {
+                marks[row][col] = Mark::Match;
+                // numbers shared between symbols only count once across them all, so there's
+                // no per symbol figure to show
+                if query.scope == Scope::EachSymbol {
+                    let values = around.iter().map(|&n| self.numbers()[n].value);
+                    let result = match query.aggregate.apply(values.clone()) {
+                        Some(result) => result.to_string(),
+                        None => "too big".to_string(),
+                    };
+                    let values = values.map(|v| v.to_string()).collect::<Vec<_>>();
+                    let expr = match query.aggregate {
+                        Aggregate::Sum => values.join(" + "),
+                        Aggregate::Product => values.join(" x "),
+                        Aggregate::Count => format!("count({})", values.join(", ")),
+                    };
+                    panels[row].push(format!(
+                        "{} {}:{} {} = {}",
+                        symbol.kind,
+                        row + 1,
+                        symbol.columns.start + 1,
+                        expr,
+                        result
+                    ));
+                }
+            } else {
+                marks[row][col] = Mark::Symbol;
+            }
+        }
+        for (n, number) in self.numbers().iter().enumerate() {
+            let mark = if is_part[n] { Mark::Part } else { Mark::Loose };
+            for (row, col) in number.run.cells() {
+                marks[row][col] = mark;
+            }
+        }
+
+        // how wide each row looks, which isn't its length once tabs or wide characters show up
+        let widths = (0..grid.height())
+            .map(|row| self.row_width(row))
+            .collect::<Vec<_>>();
+        let width = widths.iter().copied().max().unwrap_or(0);
+        let mut out = String::new();
+        for (row, row_marks) in marks.iter().enumerate() {
+            let cells = grid.row(row).unwrap_or_default();
+            match style {
+                Style::Color => paint(&mut out, cells, row_marks),
+                Style::Plain => out.extend(cells),
+            }
+            if !panels[row].is_empty() {
+                out.extend(iter::repeat_n(' ', width - widths[row] + 2));
+                let panel = panels[row].join("; ");
+                match style {
+                    Style::Color => {
+                        out.push_str(Mark::Match.color().unwrap_or_default());
+                        out.push_str(&panel);
+                        out.push_str(RESET);
+                    }
+                    Style::Plain => out.push_str(&panel),
+                }
+            }
+            out.push('\n');
+
+            if style == Style::Plain {
+                let mut markers = String::new();
+                let mut last = None;
+                for (mark, columns) in row_marks.iter().zip(self.columns(row)) {
+                    // every character of a cluster shares its columns, only the first is marked
+                    if last == Some(columns) || columns.width() == 0 {
+                        continue;
+                    }
+                    last = Some(columns);
+                    markers.push(mark.marker());
+                    markers.extend(iter::repeat_n(' ', columns.width() - 1));
+                }
+                let markers = markers.trim_end();
+                if !markers.is_empty() {
+                    out.push_str(markers);
+                    out.push('\n');
+                }
+            }
+        }
+        if style == Style::Plain {
+            out.push_str("(^ part number, x not next to a symbol, M matched by the query)\n");
+        }
+        out
+    }
+}
+
+/// a row of cells, switching colors only where the mark changes
+fn paint(out: &mut String, cells: &[char], marks: &[Mark]) {
+    let mut current = Mark::Blank;
+    for (&c, &mark) in cells.iter().zip(marks) {
+        if mark != current {
+            if current.color().is_some() {
+                out.push_str(RESET);
+            }
+            if let Some(color) = mark.color() {
+                out.push_str(color);
+            }
+            current = mark;
+        }
+        out.push(c);
+    }
+    if current.color().is_some() {
+        out.push_str(RESET);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schematic::{Neighbors, SymbolSet};
+
+    /// rendered for part 2
+    fn render(input: &str, style: Style) -> String {
+        Schematic::parse(input, &SymbolSet::Default)
+            .unwrap()
+            .render(style, &Query::gear_ratios())
+    }
+
+    #[test]
+    fn colors_start_and_reset_where_the_mark_changes() {
+        let rendered = render("467..114..\n...*......\n..35..633.", Style::Color);
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "\x1b[32m467\x1b[0m..\x1b[31m114\x1b[0m..");
+        assert_eq!(
+            lines[1],
+            "...\x1b[1;33m*\x1b[0m......  \x1b[1;33m* 2:4 467 x 35 = 16345\x1b[0m"
+        );
+        assert_eq!(lines[2], "..\x1b[32m35\x1b[0m..\x1b[31m633\x1b[0m.");
+        assert!(!rendered.contains("(^ part number"));
+    }
+
+    #[test]
+    fn neighbouring_marks_reset_between_them() {
+        // a part number running into its symbol, and a row ending on a colored cell
+        assert_eq!(
+            render("12#", Style::Color),
+            "\x1b[32m12\x1b[0m\x1b[36m#\x1b[0m\n"
+        );
+        assert_eq!(render("..", Style::Color), "..\n");
+    }
+
+    #[test]
+    fn gear_panels_line_up_past_the_widest_row() {
+        let rendered = render("12\n*\n3\n..........", Style::Plain);
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "12",
+                "^^",
+                "*           * 2:1 12 x 3 = 36",
+                "M",
+                "3",
+                "^",
+                "..........",
+                "(^ part number, x not next to a symbol, M matched by the query)",
+            ]
+        );
+    }
+
+    #[test]
+    fn gears_on_one_row_share_a_panel() {
+        let rendered = render("1.2\n*.*\n3.4", Style::Plain);
+        assert_eq!(
+            rendered.lines().nth(2),
+            Some("*.*  * 2:1 1 x 3 = 3; * 2:3 2 x 4 = 8")
+        );
+        assert_eq!(rendered.lines().nth(3), Some("M M"));
+    }
+
+    #[test]
+    fn marks_follow_the_query() {
+        let schematic = Schematic::parse("12#3\n*..4\n5*..", &SymbolSet::Default).unwrap();
+        let query = Query {
+            kinds: Some(vec!['#']),
+            neighbors: Neighbors::AtLeast(2),
+            scope: Scope::EachSymbol,
+            aggregate: Aggregate::Sum,
+        };
+        let rendered = schematic.render(Style::Plain, &query);
+        let lines = rendered.lines().collect::<Vec<_>>();
+        // the `*`s would be gears for part 2 but this query only looks at `#`
+        assert_eq!(
+            lines[..6],
+            [
+                "12#3  # 1:3 12 + 3 + 4 = 19",
+                "^^M^",
+                "*..4",
+                "   ^",
+                "5*..",
+                "^"
+            ]
+        );
+        assert_eq!(schematic.query(&query).unwrap(), 19);
+
+        // across every number there's nothing per symbol to write down
+        let query = Query {
+            scope: Scope::AllNumbers,
+            ..Query::part_numbers()
+        };
+        let rendered = schematic.render(Style::Plain, &query);
+        assert_eq!(
+            rendered.lines().take(6).collect::<Vec<_>>(),
+            ["12#3", "^^M^", "*..4", "M  ^", "5*..", "^M"]
+        );
+    }
+
+    #[test]
+    fn styles_parse() {
+        assert_eq!("Colour".parse(), Ok(Style::Color));
+        assert_eq!("plain".parse(), Ok(Style::Plain));
+        assert!("bold".parse::<Style>().is_err());
+    }
+}